@@ -261,6 +261,12 @@ mod build_bundled {
         if cfg!(feature = "session") {
             cfg.flag("-DSQLITE_ENABLE_SESSION");
         }
+        if cfg!(feature = "snapshot") {
+            cfg.flag("-DSQLITE_ENABLE_SNAPSHOT");
+        }
+        if cfg!(feature = "scanstatus") {
+            cfg.flag("-DSQLITE_ENABLE_STMT_SCANSTATUS");
+        }
 
         if let Ok(limit) = env::var("SQLITE_MAX_VARIABLE_NUMBER") {
             cfg.flag(&format!("-DSQLITE_MAX_VARIABLE_NUMBER={}", limit));
@@ -551,6 +557,12 @@ mod bindings {
         if cfg!(feature = "session") {
             bindings = bindings.clang_arg("-DSQLITE_ENABLE_SESSION");
         }
+        if cfg!(feature = "snapshot") {
+            bindings = bindings.clang_arg("-DSQLITE_ENABLE_SNAPSHOT");
+        }
+        if cfg!(feature = "scanstatus") {
+            bindings = bindings.clang_arg("-DSQLITE_ENABLE_STMT_SCANSTATUS");
+        }
         if win_target() && cfg!(feature = "winsqlite3") {
             bindings = bindings
                 .clang_arg("-DBINDGEN_USE_WINSQLITE3")
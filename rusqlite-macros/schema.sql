@@ -0,0 +1,10 @@
+-- Schema used by the tests of the `query!` macro.
+CREATE TABLE person (
+    id INTEGER PRIMARY KEY,
+    name VARCHAR(64) NOT NULL,
+    age INT,
+    score DOUBLE,
+    photo BLOB,
+    active BOOLEAN,
+    created_at DATETIME
+);
@@ -0,0 +1,189 @@
+//! Parsing of the `#[rusqlite(...)]` attributes.
+
+use syn::ext::IdentExt;
+use syn::{Attribute, Error, Ident, Lit, Meta, NestedMeta, Path, Result};
+
+/// Attributes accepted on a struct or an enum.
+pub const CONTAINER: &[&str] = &["rename_all", "integer"];
+/// Attributes accepted on a field.
+pub const FIELD: &[&str] = &["rename", "skip", "default", "index", "from_sql", "to_sql"];
+/// Attributes accepted on a variant.
+pub const VARIANT: &[&str] = &["rename"];
+
+/// Attributes of a container, a field or a variant. Several derives share the
+/// same attributes, so each derive ignores the ones it doesn't use.
+#[derive(Default)]
+pub struct Attrs {
+    pub rename: Option<String>,
+    pub rename_all: Option<RenameRule>,
+    pub skip: bool,
+    /// `Some(None)` for `default`, `Some(Some(path))` for `default = "path"`
+    pub default: Option<Option<Path>>,
+    pub index: Option<usize>,
+    pub from_sql: Option<Path>,
+    pub to_sql: Option<Path>,
+    pub integer: bool,
+}
+
+impl Attrs {
+    pub fn parse(attrs: &[Attribute], allowed: &[&str]) -> Result<Attrs> {
+        let mut res = Attrs::default();
+        for attr in attrs.iter().filter(|a| a.path.is_ident("rusqlite")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new_spanned(meta, "expected `#[rusqlite(...)]`")),
+            };
+            for nested in list.nested {
+                let meta = match nested {
+                    NestedMeta::Meta(meta) => meta,
+                    NestedMeta::Lit(lit) => {
+                        return Err(Error::new_spanned(lit, "unexpected literal"))
+                    }
+                };
+                let name = match meta.path().get_ident() {
+                    Some(ident) => ident.to_string(),
+                    None => return Err(Error::new_spanned(meta.path(), "unknown attribute")),
+                };
+                if !allowed.contains(&name.as_str()) {
+                    return Err(Error::new_spanned(
+                        meta.path(),
+                        format!("attribute `{}` is not supported here", name),
+                    ));
+                }
+                match (name.as_str(), &meta) {
+                    ("skip", Meta::Path(_)) => res.skip = true,
+                    ("integer", Meta::Path(_)) => res.integer = true,
+                    ("default", Meta::Path(_)) => res.default = Some(None),
+                    ("default", Meta::NameValue(nv)) => res.default = Some(Some(path(&nv.lit)?)),
+                    ("rename", Meta::NameValue(nv)) => res.rename = Some(string(&nv.lit)?),
+                    ("rename_all", Meta::NameValue(nv)) => {
+                        res.rename_all = Some(RenameRule::parse(&nv.lit)?)
+                    }
+                    ("index", Meta::NameValue(nv)) => match nv.lit {
+                        Lit::Int(ref i) => res.index = Some(i.base10_parse()?),
+                        ref lit => return Err(Error::new_spanned(lit, "expected an integer")),
+                    },
+                    ("from_sql", Meta::NameValue(nv)) => res.from_sql = Some(path(&nv.lit)?),
+                    ("to_sql", Meta::NameValue(nv)) => res.to_sql = Some(path(&nv.lit)?),
+                    _ => {
+                        return Err(Error::new_spanned(
+                            meta,
+                            format!("invalid `{}` attribute", name),
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// SQL name of the field or variant `ident`, given the attributes of its
+    /// container.
+    pub fn name(&self, ident: &Ident, container: &Attrs) -> String {
+        if let Some(ref name) = self.rename {
+            return name.clone();
+        }
+        let name = ident.unraw().to_string();
+        match container.rename_all {
+            Some(rule) => rule.apply(&name),
+            None => name,
+        }
+    }
+}
+
+fn string(lit: &Lit) -> Result<String> {
+    match *lit {
+        Lit::Str(ref s) => Ok(s.value()),
+        _ => Err(Error::new_spanned(lit, "expected a string")),
+    }
+}
+
+fn path(lit: &Lit) -> Result<Path> {
+    match *lit {
+        Lit::Str(ref s) => s.parse(),
+        _ => Err(Error::new_spanned(lit, "expected a path in a string")),
+    }
+}
+
+/// Case conversion of `rename_all`.
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    Camel,
+    Pascal,
+}
+
+impl RenameRule {
+    fn parse(lit: &Lit) -> Result<RenameRule> {
+        Ok(match string(lit)?.as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "camelCase" => RenameRule::Camel,
+            "PascalCase" => RenameRule::Pascal,
+            _ => {
+                return Err(Error::new_spanned(
+                    lit,
+                    "expected one of \"lowercase\", \"UPPERCASE\", \"snake_case\", \
+                     \"SCREAMING_SNAKE_CASE\", \"kebab-case\", \"camelCase\" or \"PascalCase\"",
+                ))
+            }
+        })
+    }
+
+    fn apply(self, name: &str) -> String {
+        let words = words(name);
+        match self {
+            RenameRule::Lower => name.to_lowercase(),
+            RenameRule::Upper => name.to_uppercase(),
+            RenameRule::Snake => join(&words, "_", str::to_lowercase),
+            RenameRule::ScreamingSnake => join(&words, "_", str::to_uppercase),
+            RenameRule::Kebab => join(&words, "-", str::to_lowercase),
+            RenameRule::Camel => {
+                let mut res = words.first().map(|w| w.to_lowercase()).unwrap_or_default();
+                res.push_str(&join(&words[1..], "", capitalize));
+                res
+            }
+            RenameRule::Pascal => join(&words, "", capitalize),
+        }
+    }
+}
+
+// Split `name` on underscores and on lower to upper case transitions.
+fn words(name: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    for part in name.split('_').filter(|p| !p.is_empty()) {
+        let mut start = 0;
+        let mut prev_lower = false;
+        for (i, c) in part.char_indices() {
+            if c.is_uppercase() && prev_lower {
+                words.push(&part[start..i]);
+                start = i;
+            }
+            prev_lower = c.is_lowercase() || c.is_numeric();
+        }
+        words.push(&part[start..]);
+    }
+    words
+}
+
+fn join(words: &[&str], sep: &str, f: impl Fn(&str) -> String) -> String {
+    words.iter().map(|w| f(w)).collect::<Vec<_>>().join(sep)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,79 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Error, Fields, Result};
+
+use crate::attr::{self, Attrs};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Attrs::parse(&input.attrs, attr::CONTAINER)?;
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "FromRow can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut values = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let attrs = Attrs::parse(&field.attrs, attr::FIELD)?;
+        let value = if attrs.skip {
+            quote!(::std::default::Default::default())
+        } else {
+            let idx = match (&field.ident, attrs.index) {
+                (_, Some(index)) => quote!(#index),
+                (Some(ident), None) => {
+                    let name = attrs.name(ident, &container);
+                    quote!(#name)
+                }
+                (None, None) => quote!(#i),
+            };
+            let get = match attrs.from_sql {
+                Some(ref f) => quote!(row.get_with(#idx, #f)),
+                None => quote!(row.get(#idx)),
+            };
+            match attrs.default {
+                None => quote!(#get?),
+                Some(ref default) => {
+                    let default = match default {
+                        Some(f) => quote!(#f()),
+                        None => quote!(::std::default::Default::default()),
+                    };
+                    quote! {
+                        match #get {
+                            ::std::result::Result::Err(::rusqlite::Error::InvalidColumnName(_))
+                            | ::std::result::Result::Err(::rusqlite::Error::InvalidColumnIndex(_)) => #default,
+                            res => res?,
+                        }
+                    }
+                }
+            }
+        };
+        values.push(match field.ident {
+            Some(ref ident) => quote!(#ident: #value),
+            None => value,
+        });
+    }
+    let value = match fields {
+        Fields::Named(_) => quote!(Self { #(#values),* }),
+        Fields::Unnamed(_) => quote!(Self(#(#values),*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    generics.params.insert(0, parse_quote!('__row));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::std::convert::TryFrom<&'__row ::rusqlite::Row<'__row>> for #name #ty_generics #where_clause {
+            type Error = ::rusqlite::Error;
+
+            fn try_from(row: &'__row ::rusqlite::Row<'__row>) -> ::rusqlite::Result<Self> {
+                ::std::result::Result::Ok(#value)
+            }
+        }
+    })
+}
@@ -0,0 +1,148 @@
+//! Derive macros for [rusqlite](https://docs.rs/rusqlite), re-exported by
+//! `rusqlite` when its `derive` feature is enabled.
+//!
+//! All the derives are configured with `#[rusqlite(...)]` attributes:
+//!
+//! | Attribute | Applies to | Meaning |
+//! |-----------|------------|---------|
+//! | `rename_all = "..."` | struct or enum | Case conversion of the field (or variant) names: `"lowercase"`, `"UPPERCASE"`, `"snake_case"`, `"SCREAMING_SNAKE_CASE"`, `"kebab-case"`, `"camelCase"` or `"PascalCase"` |
+//! | `integer` | enum | Store the enum as its discriminant instead of its variant name |
+//! | `rename = "..."` | field or variant | Column, parameter or text value used instead of the name |
+//! | `skip` | field | Ignore the field (`FromRow` uses `Default::default()`) |
+//! | `default`, `default = "path"` | field | `FromRow`: value used when the column does not exist |
+//! | `index = N` | field | `FromRow`: read the column at index `N` instead of by name |
+//! | `from_sql = "path"` | field | `FromRow`: read the column with `fn(ValueRef<'_>) -> FromSqlResult<T>` |
+//! | `to_sql = "path"` | field | `ToParams`: bind the field with `fn(&T) -> Result<ToSqlOutput<'_>>` |
+#![warn(rust_2018_idioms)]
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod attr;
+mod from_row;
+#[cfg(feature = "query")]
+mod query;
+mod sql;
+mod to_params;
+
+/// Derive `TryFrom<&Row<'_>>`, so that a struct can be built from each row of
+/// a query.
+///
+/// Named fields are read from the column with the same name, tuple struct
+/// fields from the column at the same index.
+///
+/// ```rust
+/// use rusqlite::{Connection, FromRow, Result};
+/// use std::convert::TryFrom;
+///
+/// #[derive(FromRow)]
+/// #[rusqlite(rename_all = "camelCase")]
+/// struct Person {
+///     id: i64,
+///     full_name: String,
+///     #[rusqlite(default)]
+///     email: Option<String>,
+/// }
+///
+/// fn people(conn: &Connection) -> Result<Vec<Person>> {
+///     let mut stmt = conn.prepare("SELECT id, fullName FROM person")?;
+///     let rows = stmt.query_map([], |row| Person::try_from(row))?;
+///     rows.collect()
+/// }
+/// ```
+#[proc_macro_derive(FromRow, attributes(rusqlite))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(from_row::expand(&input))
+}
+
+/// Derive `ToParams`, so that a reference to a struct can be used as named
+/// parameters: each field is bound to the parameter with the same name,
+/// prefixed with `:`.
+///
+/// ```rust
+/// use rusqlite::{Connection, Result, ToParams};
+///
+/// #[derive(ToParams)]
+/// struct Person {
+///     id: i64,
+///     #[rusqlite(rename = "@name")]
+///     name: String,
+///     #[rusqlite(skip)]
+///     cached: bool,
+/// }
+///
+/// fn insert(conn: &Connection, person: &Person) -> Result<usize> {
+///     conn.execute("INSERT INTO person (id, name) VALUES (:id, @name)", person)
+/// }
+/// ```
+#[proc_macro_derive(ToParams, attributes(rusqlite))]
+pub fn derive_to_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(to_params::expand(&input))
+}
+
+/// Derive `ToSql` for a newtype, delegating to its field, or for an enum
+/// without fields, stored as the name of the variant (or as its discriminant
+/// with `#[rusqlite(integer)]`).
+///
+/// ```rust
+/// use rusqlite::types::{FromSql, ToSql};
+///
+/// #[derive(ToSql, FromSql)]
+/// struct UserId(i64);
+///
+/// #[derive(ToSql, FromSql)]
+/// #[rusqlite(rename_all = "lowercase")]
+/// enum Status {
+///     Active,
+///     #[rusqlite(rename = "gone")]
+///     Deleted,
+/// }
+///
+/// #[derive(ToSql, FromSql)]
+/// #[rusqlite(integer)]
+/// enum Priority {
+///     Low = 1,
+///     High = 10,
+/// }
+/// ```
+#[proc_macro_derive(ToSql, attributes(rusqlite))]
+pub fn derive_to_sql(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(sql::expand_to_sql(&input))
+}
+
+/// Derive `FromSql` for a newtype or an enum without fields, see
+/// [`ToSql`](derive.ToSql.html).
+///
+/// Reading a value which matches no variant fails with
+/// `FromSqlError::Other`.
+#[proc_macro_derive(FromSql, attributes(rusqlite))]
+pub fn derive_from_sql(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(sql::expand_from_sql(&input))
+}
+
+/// Check `sql` against the schema of the crate at compile time, and build a
+/// `rusqlite::query::Query` binding `args` to its positional parameters.
+///
+/// See the documentation of the `rusqlite::query` module.
+///
+/// ```rust
+/// // checked against `schema.sql`
+/// let by_id = rusqlite::query!("SELECT name FROM person WHERE id = ?", 1);
+/// ```
+///
+/// SQL which cannot be prepared, or a wrong number of arguments, is rejected
+/// at compile time with an error pointing at the SQL literal.
+#[cfg(feature = "query")]
+#[proc_macro]
+pub fn query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as query::Input);
+    expand(query::expand(&input))
+}
+
+fn expand(res: syn::Result<proc_macro2::TokenStream>) -> TokenStream {
+    res.unwrap_or_else(syn::Error::into_compile_error).into()
+}
@@ -0,0 +1,311 @@
+//! `query!`: SQL prepared at compile time against the schema of the crate.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::path::PathBuf;
+use std::ptr;
+
+use libsqlite3_sys as ffi;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Error, Expr, Ident, LitStr, Result, Token};
+
+pub struct Input {
+    sql: LitStr,
+    args: Punctuated<Expr, Token![,]>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream<'_>) -> Result<Input> {
+        let sql = input.parse()?;
+        let args = if input.is_empty() {
+            Punctuated::new()
+        } else {
+            input.parse::<Token![,]>()?;
+            Punctuated::parse_terminated(input)?
+        };
+        Ok(Input { sql, args })
+    }
+}
+
+pub fn expand(input: &Input) -> Result<TokenStream> {
+    let schema_path = schema_path().map_err(|msg| Error::new(Span::call_site(), msg))?;
+    let schema = std::fs::read_to_string(&schema_path).map_err(|err| {
+        Error::new(
+            Span::call_site(),
+            format!("cannot read {}: {}", schema_path.display(), err),
+        )
+    })?;
+    let sql = input.sql.value();
+    let stmt = Database::open(&schema)
+        .and_then(|db| db.describe(&sql))
+        .map_err(|msg| Error::new_spanned(&input.sql, msg))?;
+    if stmt.params != input.args.len() {
+        return Err(Error::new_spanned(
+            &input.sql,
+            format!(
+                "expected {} parameters, got {}",
+                stmt.params,
+                input.args.len()
+            ),
+        ));
+    }
+
+    let mut fields = Vec::new();
+    let mut types = Vec::new();
+    for column in &stmt.columns {
+        let (name, not_null) = if let Some(name) = column.name.strip_suffix('!') {
+            (name, true)
+        } else if let Some(name) = column.name.strip_suffix('?') {
+            (name, false)
+        } else {
+            (column.name.as_str(), column.not_null)
+        };
+        let field = field_name(name);
+        if fields.contains(&field) {
+            return Err(Error::new_spanned(
+                &input.sql,
+                format!("duplicate column `{}`, use `AS` to rename it", name),
+            ));
+        }
+        let ty = match rust_type(column.decl_type.as_deref()) {
+            // `Value` can already represent `NULL`
+            Some(ty) if !not_null => quote!(::std::option::Option<#ty>),
+            Some(ty) => ty,
+            None => quote!(::rusqlite::types::Value),
+        };
+        types.push(ty);
+        fields.push(field);
+    }
+    let indexes = 0..fields.len();
+    let args = input.args.iter();
+    let schema_path = schema_path.to_string_lossy();
+    let sql = &input.sql;
+    Ok(quote! {{
+        // rebuild when the schema changes
+        const _: &str = ::std::include_str!(#schema_path);
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Record {
+            #(#fields: #types,)*
+        }
+
+        ::rusqlite::query::Query::new(#sql, |row: &::rusqlite::Row<'_>| {
+            ::std::result::Result::Ok(Record {
+                #(#fields: row.get(#indexes)?,)*
+            })
+        })
+        #(.arg(#args))*
+    }})
+}
+
+fn schema_path() -> std::result::Result<PathBuf, String> {
+    let dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .ok_or_else(|| "CARGO_MANIFEST_DIR is not set".to_owned())?;
+    let file = std::env::var_os("RUSQLITE_SCHEMA").unwrap_or_else(|| "schema.sql".into());
+    Ok(PathBuf::from(dir).join(file))
+}
+
+// Identifier of the field storing the column `name`.
+fn field_name(name: &str) -> Ident {
+    let mut field: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if field.is_empty() || field.starts_with(|c: char| c.is_numeric()) {
+        field.insert(0, '_');
+    }
+    match field.as_str() {
+        "_" | "self" | "Self" | "super" | "crate" => {
+            field.push('_');
+            Ident::new(&field, Span::call_site())
+        }
+        _ => syn::parse_str(&field).unwrap_or_else(|_| Ident::new_raw(&field, Span::call_site())),
+    }
+}
+
+// Type of a column with the declared type `decl_type`, `None` for
+// `rusqlite::types::Value`.
+fn rust_type(decl_type: Option<&str>) -> Option<TokenStream> {
+    let decl_type = decl_type.unwrap_or_default().to_uppercase();
+    let contains = |words: &[&str]| words.iter().any(|w| decl_type.contains(w));
+    if decl_type.is_empty() {
+        None
+    } else if contains(&["INT"]) {
+        Some(quote!(i64))
+    } else if contains(&["CHAR", "CLOB", "TEXT"]) {
+        Some(quote!(::std::string::String))
+    } else if contains(&["BLOB"]) {
+        Some(quote!(::std::vec::Vec<u8>))
+    } else if contains(&["REAL", "FLOA", "DOUB"]) {
+        Some(quote!(f64))
+    } else if contains(&["BOOL"]) {
+        Some(quote!(bool))
+    } else {
+        None
+    }
+}
+
+// Whether `sql` may contain an outer join, whose columns can be `NULL`
+// whatever their declaration.
+fn has_outer_join(sql: &str) -> bool {
+    sql.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|word| {
+            ["LEFT", "RIGHT", "FULL", "OUTER"]
+                .iter()
+                .any(|kw| word.eq_ignore_ascii_case(kw))
+        })
+}
+
+struct Statement {
+    params: usize,
+    columns: Vec<Column>,
+}
+
+struct Column {
+    name: String,
+    decl_type: Option<String>,
+    // `NOT NULL` (or `INTEGER PRIMARY KEY`) column of a table
+    not_null: bool,
+}
+
+struct Database(*mut ffi::sqlite3);
+
+impl Database {
+    fn open(schema: &str) -> std::result::Result<Database, String> {
+        let mut db = ptr::null_mut();
+        let flags = ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE;
+        let rc = unsafe {
+            ffi::sqlite3_open_v2(":memory:\0".as_ptr().cast(), &mut db, flags, ptr::null())
+        };
+        let db = Database(db);
+        if rc != ffi::SQLITE_OK {
+            return Err(db.error("cannot open database"));
+        }
+        let schema = CString::new(schema).map_err(|_| "schema contains a nul byte".to_owned())?;
+        let rc = unsafe {
+            ffi::sqlite3_exec(
+                db.0,
+                schema.as_ptr(),
+                None,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(db.error("invalid schema"));
+        }
+        Ok(db)
+    }
+
+    fn describe(&self, sql: &str) -> std::result::Result<Statement, String> {
+        let c_sql = CString::new(sql).map_err(|_| "SQL contains a nul byte".to_owned())?;
+        let mut stmt = ptr::null_mut();
+        let mut tail: *const c_char = ptr::null();
+        let rc =
+            unsafe { ffi::sqlite3_prepare_v2(self.0, c_sql.as_ptr(), -1, &mut stmt, &mut tail) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.error("invalid SQL"));
+        }
+        if stmt.is_null() {
+            return Err("SQL contains no statement".to_owned());
+        }
+        let rest = unsafe { CStr::from_ptr(tail) }.to_string_lossy();
+        let res = if !rest.trim().is_empty() {
+            Err("SQL contains more than one statement".to_owned())
+        } else {
+            unsafe {
+                let params = ffi::sqlite3_bind_parameter_count(stmt) as usize;
+                let outer_join = has_outer_join(sql);
+                let columns = (0..ffi::sqlite3_column_count(stmt))
+                    .map(|i| Column {
+                        name: text(ffi::sqlite3_column_name(stmt, i)).unwrap_or_default(),
+                        decl_type: text(ffi::sqlite3_column_decltype(stmt, i)),
+                        not_null: !outer_join && self.not_null(stmt, i),
+                    })
+                    .collect();
+                Ok(Statement { params, columns })
+            }
+        };
+        unsafe { ffi::sqlite3_finalize(stmt) };
+        res
+    }
+
+    // Whether the column `i` of `stmt` is a table column which cannot be
+    // `NULL`.
+    unsafe fn not_null(&self, stmt: *mut ffi::sqlite3_stmt, i: c_int) -> bool {
+        let db_name = ffi::sqlite3_column_database_name(stmt, i);
+        let table = ffi::sqlite3_column_table_name(stmt, i);
+        let column = ffi::sqlite3_column_origin_name(stmt, i);
+        if db_name.is_null() || table.is_null() || column.is_null() {
+            // expression
+            return false;
+        }
+        let mut not_null = 0;
+        let mut primary_key = 0;
+        let rc = ffi::sqlite3_table_column_metadata(
+            self.0,
+            db_name,
+            table,
+            column,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut not_null,
+            &mut primary_key,
+            ptr::null_mut(),
+        );
+        if rc != ffi::SQLITE_OK {
+            return false;
+        }
+        not_null != 0 || (primary_key != 0 && self.is_rowid(db_name, table, column))
+    }
+
+    // Whether `column` is an alias for the rowid of `table` (`INTEGER PRIMARY
+    // KEY`), which cannot be `NULL`.
+    unsafe fn is_rowid(
+        &self,
+        db_name: *const c_char,
+        table: *const c_char,
+        column: *const c_char,
+    ) -> bool {
+        let quote = |p| text(p).unwrap_or_default().replace('"', "\"\"");
+        let sql = format!(
+            "SELECT rowid FROM \"{}\".\"{}\"",
+            quote(db_name),
+            quote(table)
+        );
+        let sql = match CString::new(sql) {
+            Ok(sql) => sql,
+            Err(_) => return false,
+        };
+        let mut stmt = ptr::null_mut();
+        let rc = ffi::sqlite3_prepare_v2(self.0, sql.as_ptr(), -1, &mut stmt, ptr::null_mut());
+        // `WITHOUT ROWID` tables fail to prepare
+        let res = rc == ffi::SQLITE_OK
+            && !stmt.is_null()
+            && text(ffi::sqlite3_column_origin_name(stmt, 0)) == text(column);
+        ffi::sqlite3_finalize(stmt);
+        res
+    }
+
+    fn error(&self, context: &str) -> String {
+        let msg = unsafe { text(ffi::sqlite3_errmsg(self.0)) };
+        format!("{}: {}", context, msg.unwrap_or_default())
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_close(self.0) };
+    }
+}
+
+unsafe fn text(p: *const c_char) -> Option<String> {
+    if p.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(p).to_string_lossy().into_owned())
+    }
+}
@@ -0,0 +1,165 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Data, DataEnum, DeriveInput, Error, Fields, Ident, Result};
+
+use crate::attr::{self, Attrs};
+
+pub fn expand_to_sql(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    match input.data {
+        Data::Enum(ref data) => {
+            let (variants, values) = variants(input, data)?;
+            let values = values
+                .iter()
+                .zip(&variants)
+                .map(|(value, variant)| match value {
+                    Value::Text(s) => quote!(#s),
+                    Value::Integer => quote!(Self::#variant as i64),
+                });
+            Ok(quote! {
+                impl ::rusqlite::types::ToSql for #name {
+                    fn to_sql(&self) -> ::rusqlite::Result<::rusqlite::types::ToSqlOutput<'_>> {
+                        ::std::result::Result::Ok(::rusqlite::types::ToSqlOutput::from(match self {
+                            #(Self::#variants => #values,)*
+                        }))
+                    }
+                }
+            })
+        }
+        _ => {
+            let (member, ty) = newtype(input)?;
+            let mut generics = input.generics.clone();
+            generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote!(#ty: ::rusqlite::types::ToSql));
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+            Ok(quote! {
+                impl #impl_generics ::rusqlite::types::ToSql for #name #ty_generics #where_clause {
+                    #[inline]
+                    fn to_sql(&self) -> ::rusqlite::Result<::rusqlite::types::ToSqlOutput<'_>> {
+                        ::rusqlite::types::ToSql::to_sql(&self.#member)
+                    }
+                }
+            })
+        }
+    }
+}
+
+pub fn expand_from_sql(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    match input.data {
+        Data::Enum(ref data) => {
+            let (variants, values) = variants(input, data)?;
+            let type_name = name.to_string();
+            let body = if values[0] == Value::Integer {
+                quote! {
+                    let value = value.as_i64()?;
+                    #(
+                        if value == Self::#variants as i64 {
+                            return ::std::result::Result::Ok(Self::#variants);
+                        }
+                    )*
+                    ::std::result::Result::Err(::rusqlite::types::FromSqlError::Other(
+                        ::std::format!("invalid {} value: {}", #type_name, value).into(),
+                    ))
+                }
+            } else {
+                let values = values.iter().map(|value| match value {
+                    Value::Text(s) => s,
+                    Value::Integer => unreachable!(),
+                });
+                quote! {
+                    match value.as_str()? {
+                        #(#values => ::std::result::Result::Ok(Self::#variants),)*
+                        value => ::std::result::Result::Err(::rusqlite::types::FromSqlError::Other(
+                            ::std::format!("invalid {} value: {:?}", #type_name, value).into(),
+                        )),
+                    }
+                }
+            };
+            Ok(quote! {
+                impl ::rusqlite::types::FromSql for #name {
+                    fn column_result(
+                        value: ::rusqlite::types::ValueRef<'_>,
+                    ) -> ::rusqlite::types::FromSqlResult<Self> {
+                        #body
+                    }
+                }
+            })
+        }
+        _ => {
+            let (member, ty) = newtype(input)?;
+            let mut generics = input.generics.clone();
+            generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote!(#ty: ::rusqlite::types::FromSql));
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+            Ok(quote! {
+                impl #impl_generics ::rusqlite::types::FromSql for #name #ty_generics #where_clause {
+                    #[inline]
+                    fn column_result(
+                        value: ::rusqlite::types::ValueRef<'_>,
+                    ) -> ::rusqlite::types::FromSqlResult<Self> {
+                        ::rusqlite::types::FromSql::column_result(value).map(|v| Self { #member: v })
+                    }
+                }
+            })
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Value {
+    Text(String),
+    Integer,
+}
+
+// Variants of a field-less enum, with their SQL value.
+fn variants<'a>(input: &DeriveInput, data: &'a DataEnum) -> Result<(Vec<&'a Ident>, Vec<Value>)> {
+    let container = Attrs::parse(&input.attrs, attr::CONTAINER)?;
+    if data.variants.is_empty() {
+        return Err(Error::new_spanned(&input.ident, "enum has no variants"));
+    }
+    let mut variants = Vec::new();
+    let mut values = Vec::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(variant, "variants cannot have fields"));
+        }
+        let attrs = Attrs::parse(&variant.attrs, attr::VARIANT)?;
+        if !container.integer {
+            values.push(Value::Text(attrs.name(&variant.ident, &container)));
+        } else if attrs.rename.is_none() {
+            values.push(Value::Integer);
+        } else {
+            return Err(Error::new_spanned(
+                variant,
+                "`rename` cannot be used with `integer`",
+            ));
+        }
+        variants.push(&variant.ident);
+    }
+    Ok((variants, values))
+}
+
+// Member and type of the single field of a newtype.
+fn newtype(input: &DeriveInput) -> Result<(syn::Member, &syn::Type)> {
+    Attrs::parse(&input.attrs, attr::CONTAINER)?;
+    if let Data::Struct(ref data) = input.data {
+        if data.fields.len() == 1 {
+            let field = data.fields.iter().next().unwrap();
+            Attrs::parse(&field.attrs, attr::FIELD)?;
+            let member = match field.ident {
+                Some(ref ident) => syn::Member::Named(ident.clone()),
+                None => syn::Member::Unnamed(0.into()),
+            };
+            return Ok((member, &field.ty));
+        }
+    }
+    Err(Error::new_spanned(
+        &input.ident,
+        "expected a struct with a single field or an enum without fields",
+    ))
+}
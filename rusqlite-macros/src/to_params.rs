@@ -0,0 +1,56 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Result};
+
+use crate::attr::{self, Attrs};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Attrs::parse(&input.attrs, attr::CONTAINER)?;
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "ToParams can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "ToParams can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut params = Vec::new();
+    for field in fields {
+        let attrs = Attrs::parse(&field.attrs, attr::FIELD)?;
+        if attrs.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let mut name = attrs.name(ident, &container);
+        if !name.starts_with(&[':', '@', '$'][..]) {
+            name.insert(0, ':');
+        }
+        let value = match attrs.to_sql {
+            Some(ref f) => quote!(#f(&self.#ident)?),
+            None => quote!(::rusqlite::types::ToSql::to_sql(&self.#ident)?),
+        };
+        params.push(quote!((#name, #value)));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rusqlite::params::ToParams for #name #ty_generics #where_clause {
+            fn to_params(
+                &self,
+            ) -> ::rusqlite::Result<::std::vec::Vec<(&'static str, ::rusqlite::types::ToSqlOutput<'_>)>> {
+                ::std::result::Result::Ok(::std::vec![#(#params),*])
+            }
+        }
+    })
+}
@@ -0,0 +1,78 @@
+//! `query!` checked against `schema.sql`.
+use rusqlite::types::Value;
+use rusqlite::{query, Connection, Error, Result};
+
+fn db() -> Result<Connection> {
+    let db = Connection::open_in_memory()?;
+    db.execute_batch(include_str!("../schema.sql"))?;
+    Ok(db)
+}
+
+#[test]
+fn test_query() -> Result<()> {
+    let db = db()?;
+    let name = "Ada".to_owned();
+    let inserted = query!(
+        "INSERT INTO person (name, age, active) VALUES (?, ?, ?)",
+        &name,
+        36,
+        true
+    )
+    .execute(&db)?;
+    assert_eq!(1, inserted);
+    query!("INSERT INTO person (name) VALUES (?)", "Bob").execute(&db)?;
+
+    let people =
+        query!("SELECT id, name, age, score, photo, active, created_at FROM person ORDER BY id")
+            .fetch_all(&db)?;
+    assert_eq!(2, people.len());
+    let ada = &people[0];
+    let id: i64 = ada.id;
+    let name: &String = &ada.name;
+    let age: Option<i64> = ada.age;
+    let score: Option<f64> = ada.score;
+    let photo: &Option<Vec<u8>> = &ada.photo;
+    let active: Option<bool> = ada.active;
+    let created_at: &Value = &ada.created_at;
+    assert_eq!(1, id);
+    assert_eq!("Ada", name);
+    assert_eq!(Some(36), age);
+    assert_eq!(None, score);
+    assert_eq!(&None, photo);
+    assert_eq!(Some(true), active);
+    assert_eq!(&Value::Null, created_at);
+    assert_eq!(None, people[1].age);
+
+    let count = query!("SELECT count(*) AS n, max(age) + 1 FROM person").fetch_one(&db)?;
+    assert_eq!(Value::Integer(2), count.n);
+    assert_eq!(Value::Integer(37), count.max_age____1);
+
+    // `!` and `?` override the nullability of a column
+    let ages =
+        query!("SELECT age AS \"age!\", name AS \"name?\" FROM person WHERE age IS NOT NULL")
+            .fetch_all(&db)?;
+    let age: i64 = ages[0].age;
+    let name: &Option<String> = &ages[0].name;
+    assert_eq!(36, age);
+    assert_eq!(&Some("Ada".to_owned()), name);
+
+    // columns of an outer join can be `NULL`
+    let pairs = query!(
+        "SELECT a.name AS a, b.name AS b FROM person a LEFT JOIN person b ON b.age > a.age ORDER BY a.id"
+    )
+    .fetch_all(&db)?;
+    assert_eq!(Some("Ada".to_owned()), pairs[0].a);
+    assert_eq!(None, pairs[0].b);
+
+    let nobody = query!(
+        "SELECT name FROM person WHERE age > ?1 AND age < ?1 + 10",
+        100
+    )
+    .fetch_optional(&db)?;
+    assert!(nobody.is_none());
+    assert!(matches!(
+        query!("SELECT name FROM person WHERE id = ?", 3).fetch_one(&db),
+        Err(Error::QueryReturnedNoRows)
+    ));
+    Ok(())
+}
@@ -0,0 +1,12 @@
+//! Diagnostics of `query!`, checked with `trybuild`.
+
+#[test]
+fn test_query_errors() {
+    // the test crates are built elsewhere: point them to our schema
+    std::env::set_var(
+        "RUSQLITE_SCHEMA",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/schema.sql"),
+    );
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
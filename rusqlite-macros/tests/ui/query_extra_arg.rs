@@ -0,0 +1,3 @@
+fn main() {
+    let _ = rusqlite::query!("SELECT name FROM person WHERE id = ?", 1, 2);
+}
@@ -0,0 +1,547 @@
+//! Bulk insertion of rows with multi-row `INSERT` statements
+//!
+//! An [`Appender`] buffers the rows appended to a table, and inserts them
+//! with statements of as many rows as
+//! [`SQLITE_LIMIT_VARIABLE_NUMBER`](https://sqlite.org/limits.html#max_variable_number)
+//! allows. Unless the connection is already in a transaction, the rows are
+//! committed in batches.
+//!
+//! ```rust
+//! # use rusqlite::{params, Connection, Result};
+//! use rusqlite::appender::OnConflict;
+//!
+//! fn load(conn: &Connection) -> Result<()> {
+//!     conn.execute_batch("CREATE TABLE person(id INTEGER PRIMARY KEY, name TEXT)")?;
+//!     let mut appender = conn
+//!         .appender("person", &["id", "name"])?
+//!         .batch_size(50_000)
+//!         .on_conflict(OnConflict::Replace);
+//!     for i in 0..100_000 {
+//!         appender.append((i, format!("person {}", i)))?;
+//!     }
+//!     appender.append(params![0, "first"])?;
+//!     let stats = appender.finish()?;
+//!     assert_eq!(100_001, stats.rows);
+//!     assert_eq!(3, stats.commits);
+//!     Ok(())
+//! }
+//! # fn main() { load(&Connection::open_in_memory().unwrap()).unwrap(); }
+//! ```
+
+use std::fmt::Write;
+
+use crate::params::ToParams;
+use crate::types::{ToSql, ToSqlOutput, Value};
+use crate::{ffi, params_from_iter, Connection, Error, Result, Transaction, TransactionBehavior};
+
+/// What to do when an appended row violates a `UNIQUE` or `PRIMARY KEY`
+/// constraint.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum OnConflict {
+    /// Fail (the default)
+    Abort,
+    /// Skip the row (`INSERT OR IGNORE`)
+    Ignore,
+    /// Delete the existing row (`INSERT OR REPLACE`)
+    Replace,
+    /// Update the existing row with the values of the other columns
+    /// (`ON CONFLICT (...) DO UPDATE`, SQLite 3.24.0 or later). The conflict
+    /// target is made of the given columns.
+    Update(Vec<String>),
+}
+
+/// Counts reported by an [`Appender`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct AppenderStats {
+    /// Number of rows appended
+    pub rows: usize,
+    /// Number of rows inserted, replaced or updated, which excludes the rows
+    /// ignored on conflict
+    pub changes: usize,
+    /// Number of `INSERT` statements executed
+    pub statements: usize,
+    /// Number of transactions committed by the appender
+    pub commits: usize,
+}
+
+/// Rows accepted by [`Appender::append`]: tuples of up to 16 values,
+/// slices of values (e.g. [`params!`](crate::params!)) and references to
+/// [`ToParams`], whose values are matched to the columns by name.
+pub trait AppenderRow {
+    /// Push the value of each column, in order, to `values`.
+    fn push_values(self, columns: &[String], values: &mut Vec<Value>) -> Result<()>;
+}
+
+impl<T: ToSql> AppenderRow for &[T] {
+    fn push_values(self, columns: &[String], values: &mut Vec<Value>) -> Result<()> {
+        if self.len() != columns.len() {
+            return Err(Error::InvalidParameterCount(self.len(), columns.len()));
+        }
+        for value in self {
+            values.push(to_value(value.to_sql()?)?);
+        }
+        Ok(())
+    }
+}
+
+impl<T: ToParams + ?Sized> AppenderRow for &T {
+    fn push_values(self, columns: &[String], values: &mut Vec<Value>) -> Result<()> {
+        let mut params = self.to_params()?;
+        for column in columns {
+            let i = params
+                .iter()
+                .position(|(name, _)| name.get(1..) == Some(column.as_str()))
+                .ok_or_else(|| Error::InvalidParameterName(format!(":{}", column)))?;
+            values.push(to_value(params.swap_remove(i).1)?);
+        }
+        Ok(())
+    }
+}
+
+macro_rules! tuple_appender_row {
+    ($count:literal: $($field:ident),*) => {
+        impl<$($field,)*> AppenderRow for ($($field,)*) where $($field: ToSql,)* {
+            #[allow(non_snake_case)]
+            fn push_values(self, columns: &[String], values: &mut Vec<Value>) -> Result<()> {
+                if columns.len() != $count {
+                    return Err(Error::InvalidParameterCount($count, columns.len()));
+                }
+                let ($($field,)*) = self;
+                $(values.push(to_value($field.to_sql()?)?);)*
+                Ok(())
+            }
+        }
+    }
+}
+
+tuple_appender_row!(1: A);
+tuple_appender_row!(2: A, B);
+tuple_appender_row!(3: A, B, C);
+tuple_appender_row!(4: A, B, C, D);
+tuple_appender_row!(5: A, B, C, D, E);
+tuple_appender_row!(6: A, B, C, D, E, F);
+tuple_appender_row!(7: A, B, C, D, E, F, G);
+tuple_appender_row!(8: A, B, C, D, E, F, G, H);
+tuple_appender_row!(9: A, B, C, D, E, F, G, H, I);
+tuple_appender_row!(10: A, B, C, D, E, F, G, H, I, J);
+tuple_appender_row!(11: A, B, C, D, E, F, G, H, I, J, K);
+tuple_appender_row!(12: A, B, C, D, E, F, G, H, I, J, K, L);
+tuple_appender_row!(13: A, B, C, D, E, F, G, H, I, J, K, L, M);
+tuple_appender_row!(14: A, B, C, D, E, F, G, H, I, J, K, L, M, N);
+tuple_appender_row!(15: A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
+tuple_appender_row!(16: A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+
+// The values are buffered, so they cannot borrow from the row.
+fn to_value(output: ToSqlOutput<'_>) -> Result<Value> {
+    match output {
+        ToSqlOutput::Borrowed(v) => Ok(v.into()),
+        ToSqlOutput::Owned(v) => Ok(v),
+        #[cfg(feature = "blob")]
+        ToSqlOutput::ZeroBlob(len) => Ok(Value::Blob(vec![0; len.max(0) as usize])),
+        #[cfg(feature = "array")]
+        ToSqlOutput::Array(_) => Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_MISUSE),
+            Some("Unsupported value \"Array\"".to_owned()),
+        )),
+    }
+}
+
+/// Bulk inserter of rows into a table, see the [module](self) documentation.
+///
+/// Rows are buffered: they are inserted when a full statement is ready, when
+/// a batch is complete, on [`Appender::flush`] or [`Appender::finish`].
+/// Dropping the appender flushes it too, ignoring errors: call
+/// [`Appender::finish`] to handle them.
+///
+/// When inserting or committing fails, the buffered rows are discarded and
+/// the transaction of the current batch is rolled back (batches committed
+/// before are kept). An appender which failed, or which is dropped while
+/// panicking, is not flushed on drop.
+#[derive(Debug)]
+pub struct Appender<'conn> {
+    conn: &'conn Connection,
+    table: String,
+    columns: Vec<String>,
+    on_conflict: OnConflict,
+    batch_size: usize,
+    rows_per_statement: usize,
+    values: Vec<Value>,
+    tx: Option<Transaction<'conn>>,
+    // rows inserted by the current transaction
+    tx_rows: usize,
+    stats: AppenderStats,
+    // an insert or a commit failed
+    failed: bool,
+}
+
+impl Connection {
+    /// Create an [`Appender`] inserting rows into `columns` of `table`, or
+    /// into all its columns if `columns` is empty.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `columns` is empty and the columns of `table`
+    /// cannot be read.
+    pub fn appender(&self, table: &str, columns: &[&str]) -> Result<Appender<'_>> {
+        Appender::new(self, table, columns)
+    }
+}
+
+impl<'conn> Appender<'conn> {
+    /// See [`Connection::appender`].
+    pub fn new(conn: &'conn Connection, table: &str, columns: &[&str]) -> Result<Appender<'conn>> {
+        let mut columns: Vec<String> = columns.iter().map(|&c| c.to_owned()).collect();
+        if columns.is_empty() {
+            conn.pragma(None, "table_info", &table, |row| {
+                columns.push(row.get(1)?);
+                Ok(())
+            })?;
+            if columns.is_empty() {
+                return Err(Error::SqliteFailure(
+                    ffi::Error::new(ffi::SQLITE_ERROR),
+                    Some(format!("no such table: {}", table)),
+                ));
+            }
+        }
+        let max_variables = unsafe {
+            ffi::sqlite3_limit(conn.db.borrow().db(), ffi::SQLITE_LIMIT_VARIABLE_NUMBER, -1)
+        };
+        Ok(Appender {
+            conn,
+            table: table.to_owned(),
+            rows_per_statement: (max_variables.max(0) as usize / columns.len()).max(1),
+            columns,
+            on_conflict: OnConflict::Abort,
+            batch_size: 10_000,
+            values: Vec::new(),
+            tx: None,
+            tx_rows: 0,
+            stats: AppenderStats::default(),
+            failed: false,
+        })
+    }
+
+    /// Commit every `batch_size` rows (10,000 by default). Zero inserts all
+    /// the rows in a single transaction.
+    ///
+    /// Batches are ignored if the connection is already in a transaction
+    /// when rows are inserted.
+    pub fn batch_size(mut self, batch_size: usize) -> Appender<'conn> {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// What to do when a row conflicts with an existing one.
+    pub fn on_conflict(mut self, on_conflict: OnConflict) -> Appender<'conn> {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    /// Append a row.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the row doesn't provide one value per column or
+    /// if inserting the buffered rows fails.
+    pub fn append<R: AppenderRow>(&mut self, row: R) -> Result<()> {
+        let len = self.values.len();
+        if let Err(err) = row.push_values(&self.columns, &mut self.values) {
+            self.values.truncate(len);
+            return Err(err);
+        }
+        self.stats.rows += 1;
+        let buffered = self.buffered_rows();
+        if buffered == self.rows_per_statement || self.tx_rows + buffered == self.batch_size {
+            self.insert()?;
+        }
+        Ok(())
+    }
+
+    /// Insert the buffered rows and commit.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if inserting the rows or committing fails.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.buffered_rows() > 0 {
+            self.insert()?;
+        }
+        self.commit()
+    }
+
+    /// Flush the appender and return its counts.
+    ///
+    /// # Failure
+    ///
+    /// See [`Appender::flush`].
+    pub fn finish(mut self) -> Result<AppenderStats> {
+        self.flush()?;
+        Ok(self.stats)
+    }
+
+    /// Counts of the appender so far.
+    pub fn stats(&self) -> AppenderStats {
+        self.stats
+    }
+
+    fn buffered_rows(&self) -> usize {
+        self.values.len() / self.columns.len()
+    }
+
+    fn insert(&mut self) -> Result<()> {
+        let r = self.try_insert();
+        if r.is_err() {
+            self.abort();
+        }
+        r
+    }
+
+    fn try_insert(&mut self) -> Result<()> {
+        if self.tx.is_none() && self.conn.is_autocommit() {
+            self.tx = Some(Transaction::new_unchecked(
+                self.conn,
+                TransactionBehavior::Immediate,
+            )?);
+        }
+        let rows = self.buffered_rows();
+        let sql = self.sql(rows);
+        let changes = if rows == self.rows_per_statement {
+            let mut stmt = self.conn.prepare_cached(&sql)?;
+            stmt.execute(params_from_iter(self.values.drain(..)))?
+        } else {
+            // partial statements are rare, don't evict full ones from the cache
+            let mut stmt = self.conn.prepare(&sql)?;
+            stmt.execute(params_from_iter(self.values.drain(..)))?
+        };
+        self.stats.changes += changes;
+        self.stats.statements += 1;
+        self.tx_rows += rows;
+        if self.tx_rows == self.batch_size {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.tx_rows = 0;
+        if let Some(tx) = self.tx.take() {
+            // a failed commit is rolled back when `tx` is dropped
+            if let Err(err) = tx.commit() {
+                self.abort();
+                return Err(err);
+            }
+            self.stats.commits += 1;
+        }
+        Ok(())
+    }
+
+    // Discard the buffered rows and roll back the current batch.
+    fn abort(&mut self) {
+        self.failed = true;
+        self.values.clear();
+        self.tx_rows = 0;
+        self.tx = None;
+    }
+
+    fn sql(&self, rows: usize) -> String {
+        let mut sql = String::from("INSERT ");
+        match self.on_conflict {
+            OnConflict::Ignore => sql.push_str("OR IGNORE "),
+            OnConflict::Replace => sql.push_str("OR REPLACE "),
+            OnConflict::Abort | OnConflict::Update(_) => {}
+        }
+        sql.push_str("INTO ");
+        push_identifier(&mut sql, &self.table);
+        sql.push_str(" (");
+        push_list(&mut sql, &self.columns, |sql, column| {
+            push_identifier(sql, column)
+        });
+        sql.push_str(") VALUES ");
+        let row = format!("({})", vec!["?"; self.columns.len()].join(", "));
+        sql.push_str(&vec![row; rows].join(", "));
+        if let OnConflict::Update(ref target) = self.on_conflict {
+            sql.push_str(" ON CONFLICT (");
+            push_list(&mut sql, target, |sql, column| push_identifier(sql, column));
+            let updated: Vec<&String> = self
+                .columns
+                .iter()
+                .filter(|c| !target.contains(c))
+                .collect();
+            if updated.is_empty() {
+                sql.push_str(") DO NOTHING");
+            } else {
+                sql.push_str(") DO UPDATE SET ");
+                push_list(&mut sql, &updated, |sql, column| {
+                    push_identifier(sql, column);
+                    sql.push_str(" = excluded.");
+                    push_identifier(sql, column);
+                });
+            }
+        }
+        sql
+    }
+}
+
+impl Drop for Appender<'_> {
+    fn drop(&mut self) {
+        if self.failed || std::thread::panicking() {
+            // `self.tx` is rolled back
+            return;
+        }
+        let _ = self.flush();
+    }
+}
+
+fn push_identifier(sql: &mut String, identifier: &str) {
+    let _ = write!(sql, "\"{}\"", identifier.replace('"', "\"\""));
+}
+
+fn push_list<T>(sql: &mut String, items: &[T], mut f: impl FnMut(&mut String, &T)) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            sql.push_str(", ");
+        }
+        f(sql, item);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OnConflict;
+    use crate::{params, Connection, Error, Result};
+
+    fn db() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo(id INTEGER PRIMARY KEY, \"na\"\"me\" TEXT, n INTEGER)")?;
+        Ok(db)
+    }
+
+    fn count(db: &Connection) -> Result<i64> {
+        db.query_row("SELECT count(*) FROM foo", [], |r| r.get(0))
+    }
+
+    #[test]
+    fn test_append() -> Result<()> {
+        let db = db()?;
+        let mut appender = db.appender("foo", &[])?.batch_size(7);
+        appender.rows_per_statement = 3;
+        for i in 0..10 {
+            appender.append((i, "x", i * 2))?;
+        }
+        // two full statements, one row to complete the first batch, then
+        // another full statement
+        let stats = appender.stats();
+        assert_eq!(10, stats.rows);
+        assert_eq!(4, stats.statements);
+        assert_eq!(1, stats.commits);
+        assert_eq!(10, count(&db)?);
+        appender.append(params![10, "y", 20])?;
+        assert!(matches!(
+            appender.append((11, "z")),
+            Err(Error::InvalidParameterCount(2, 3))
+        ));
+        let stats = appender.finish()?;
+        assert_eq!(11, stats.rows);
+        assert_eq!(11, stats.changes);
+        assert_eq!(5, stats.statements);
+        assert_eq!(2, stats.commits);
+        assert_eq!(11, count(&db)?);
+        let n: i64 = db.query_row("SELECT n FROM foo WHERE \"na\"\"me\" = 'y'", [], |r| {
+            r.get(0)
+        })?;
+        assert_eq!(20, n);
+        Ok(())
+    }
+
+    #[test]
+    fn test_outer_transaction() -> Result<()> {
+        let mut db = db()?;
+        {
+            let tx = db.transaction()?;
+            let mut appender = tx.appender("foo", &["id"])?.batch_size(1);
+            appender.append(&[1][..])?;
+            appender.append(&[2][..])?;
+            assert_eq!(0, appender.finish()?.commits);
+        }
+        assert_eq!(0, count(&db)?);
+
+        // dropping the appender flushes it
+        db.appender("foo", &["id"])?.append((1,))?;
+        assert_eq!(1, count(&db)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_on_conflict() -> Result<()> {
+        let db = db()?;
+        db.execute("INSERT INTO foo VALUES (1, 'a', 1)", [])?;
+        let columns = ["id", "na\"me", "n"];
+
+        let mut appender = db
+            .appender("foo", &columns)?
+            .on_conflict(OnConflict::Ignore);
+        appender.append((1, "b", 2))?;
+        appender.append((2, "b", 2))?;
+        let stats = appender.finish()?;
+        assert_eq!((2, 1), (stats.rows, stats.changes));
+
+        let mut appender = db
+            .appender("foo", &columns)?
+            .on_conflict(OnConflict::Replace);
+        appender.append((1, "c", 3))?;
+        appender.finish()?;
+        let row: (String, Option<i64>) =
+            db.query_row("SELECT \"na\"\"me\", n FROM foo WHERE id = 1", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })?;
+        assert_eq!(("c".to_owned(), Some(3)), row);
+
+        let mut appender = db
+            .appender("foo", &["id", "n"])?
+            .on_conflict(OnConflict::Update(vec!["id".to_owned()]));
+        appender.append((1, 4))?;
+        appender.append((3, 5))?;
+        appender.finish()?;
+        let row: (String, Option<i64>) =
+            db.query_row("SELECT \"na\"\"me\", n FROM foo WHERE id = 1", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })?;
+        assert_eq!(("c".to_owned(), Some(4)), row);
+        assert_eq!(3, count(&db)?);
+
+        let mut appender = db.appender("foo", &["id"])?;
+        appender.append((1,))?;
+        assert!(appender.finish().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failure_rolls_back() -> Result<()> {
+        let db = db()?;
+        {
+            let mut appender = db.appender("foo", &["id"])?;
+            appender.rows_per_statement = 1;
+            appender.append((1,))?;
+            appender.append((2,))?;
+            appender.append((3,))?;
+            assert!(appender.append((3,)).is_err());
+            // dropping a failed appender doesn't commit
+            appender.append((4,))?;
+        }
+        assert_eq!(0, count(&db)?);
+        assert!(db.is_autocommit());
+
+        // committed batches are kept
+        let mut appender = db.appender("foo", &["id"])?.batch_size(2);
+        appender.rows_per_statement = 1;
+        appender.append((1,))?;
+        appender.append((2,))?;
+        appender.append((3,))?;
+        assert!(appender.append((1,)).is_err());
+        assert!(appender.finish().is_ok());
+        assert_eq!(2, count(&db)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+//! Executor-agnostic asynchronous access to a connection.
+//!
+//! An [`AsyncConnection`] moves a [`Connection`] to a dedicated worker thread.
+//! Closures submitted with [`call`](AsyncConnection::call) run on that
+//! thread, one after the other, and their results are delivered through
+//! futures which do not depend on any particular runtime.
+//!
+//! Dropping a pending future before its closure has started cancels it;
+//! dropping it while its closure is running interrupts the current query
+//! (see [`InterruptHandle`]).
+//!
+//! ```rust,no_run
+//! # use rusqlite::async_connection::AsyncConnection;
+//! # use rusqlite::{Connection, Result};
+//! async fn count_people(conn: &AsyncConnection) -> Result<i64> {
+//!     conn.call(|conn| conn.query_row("SELECT count(*) FROM people", [], |r| r.get(0)))
+//!         .await
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use futures_core::Stream;
+
+use crate::error::error_from_sqlite_code;
+use crate::ffi;
+use crate::{Connection, InterruptHandle, Params, Result, Row};
+
+// Number of rows buffered by a `RowStream` before the worker thread waits
+// for the consumer.
+const ROW_STREAM_CAPACITY: usize = 64;
+
+type Job = Box<dyn FnOnce(&mut Connection) + Send>;
+
+enum Message {
+    Run(Job),
+    Close(Producer<()>),
+}
+
+/// Handle to a [`Connection`] owned by a dedicated worker thread.
+///
+/// The worker thread stops when the `AsyncConnection` is dropped, once all
+/// the submitted closures have run.
+pub struct AsyncConnection {
+    sender: Mutex<mpsc::Sender<Message>>,
+    interrupt: Arc<InterruptHandle>,
+}
+
+impl AsyncConnection {
+    /// Move `conn` to a new worker thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread cannot be spawned.
+    pub fn new(conn: Connection) -> AsyncConnection {
+        let interrupt = Arc::new(conn.get_interrupt_handle());
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("rusqlite".to_owned())
+            .spawn(move || run(conn, receiver))
+            .expect("failed to spawn connection thread");
+        AsyncConnection {
+            sender: Mutex::new(sender),
+            interrupt,
+        }
+    }
+
+    /// Run `f` on the worker thread and return a future resolving to its
+    /// result.
+    ///
+    /// Closures are run in the order they were submitted.
+    pub fn call<F, T>(&self, f: F) -> Call<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (producer, channel) = Channel::new(1);
+        self.send(Message::Run(Box::new(move |conn| {
+            if producer.start() {
+                producer.push(f(conn));
+            }
+            producer.finish();
+        })));
+        Call {
+            channel,
+            interrupt: self.interrupt.clone(),
+        }
+    }
+
+    /// Run the query `sql` on the worker thread and stream its rows, each of
+    /// them converted by `f`.
+    ///
+    /// At most a few rows are buffered: the query is suspended until the
+    /// stream is polled.
+    ///
+    /// `params` must be `Send`: for a query without parameter, use
+    /// [`params_from_iter`](crate::params_from_iter) with an empty iterator
+    /// instead of `[]`.
+    pub fn query_stream<P, F, T>(&self, sql: &str, params: P, mut f: F) -> RowStream<T>
+    where
+        P: Params + Send + 'static,
+        F: FnMut(&Row<'_>) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let sql = sql.to_owned();
+        let (producer, channel) = Channel::new(ROW_STREAM_CAPACITY);
+        self.send(Message::Run(Box::new(move |conn| {
+            if producer.start() {
+                let result = (|| {
+                    let mut stmt = conn.prepare(&sql)?;
+                    let mut rows = stmt.query(params)?;
+                    while let Some(row) = rows.next()? {
+                        if !producer.push(f(row)) {
+                            // stream dropped
+                            break;
+                        }
+                    }
+                    Ok(())
+                })();
+                if let Err(err) = result {
+                    producer.push(Err(err));
+                }
+            }
+            producer.finish();
+        })));
+        RowStream {
+            channel,
+            interrupt: self.interrupt.clone(),
+        }
+    }
+
+    /// Close the connection once all the previously submitted closures have
+    /// run.
+    pub fn close(self) -> Call<()> {
+        let (producer, channel) = Channel::new(1);
+        self.send(Message::Close(producer));
+        Call {
+            channel,
+            interrupt: self.interrupt.clone(),
+        }
+    }
+
+    /// Get access to a handle that can be used to interrupt the query
+    /// currently running on the worker thread.
+    pub fn interrupt_handle(&self) -> Arc<InterruptHandle> {
+        self.interrupt.clone()
+    }
+
+    fn send(&self, message: Message) {
+        let sender = self.sender.lock().unwrap_or_else(|e| e.into_inner());
+        // If the worker thread is gone, the message is dropped together with
+        // its channel, and the receiving side reports the error.
+        let _ = sender.send(message);
+    }
+}
+
+impl fmt::Debug for AsyncConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncConnection").finish()
+    }
+}
+
+fn run(mut conn: Connection, receiver: mpsc::Receiver<Message>) {
+    while let Ok(message) = receiver.recv() {
+        match message {
+            Message::Run(job) => job(&mut conn),
+            Message::Close(producer) => {
+                if producer.start() {
+                    producer.push(conn.close().map_err(|(_, err)| err));
+                }
+                producer.finish();
+                return;
+            }
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum JobState {
+    Pending,
+    Running,
+    Done,
+}
+
+struct ChannelState<T> {
+    items: VecDeque<Result<T>>,
+    job: JobState,
+    // the job has been dropped without finishing (worker thread gone)
+    aborted: bool,
+    // receiver dropped
+    cancelled: bool,
+    waker: Option<Waker>,
+}
+
+// Single-producer single-consumer channel between a job run by the worker
+// thread and the future / stream waiting for its results.
+struct Channel<T> {
+    capacity: usize,
+    state: Mutex<ChannelState<T>>,
+    not_full: Condvar,
+}
+
+impl<T> Channel<T> {
+    fn new(capacity: usize) -> (Producer<T>, Arc<Channel<T>>) {
+        let channel = Arc::new(Channel {
+            capacity,
+            state: Mutex::new(ChannelState {
+                items: VecDeque::new(),
+                job: JobState::Pending,
+                aborted: false,
+                cancelled: false,
+                waker: None,
+            }),
+            not_full: Condvar::new(),
+        });
+        (
+            Producer {
+                channel: channel.clone(),
+                finished: false,
+            },
+            channel,
+        )
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ChannelState<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // `Ready(None)` once the job is done and all its items have been
+    // received.
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<Result<T>>> {
+        let mut state = self.lock();
+        if let Some(item) = state.items.pop_front() {
+            self.not_full.notify_one();
+            return Poll::Ready(Some(item));
+        }
+        if state.job == JobState::Done {
+            if state.aborted {
+                state.aborted = false;
+                return Poll::Ready(Some(Err(worker_gone())));
+            }
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    // Interrupts the job if it is currently running. The state stays locked
+    // meanwhile, so that the job cannot finish and the worker thread start
+    // the next one, which would be interrupted instead.
+    fn cancel(&self, interrupt: &InterruptHandle) {
+        let mut state = self.lock();
+        state.cancelled = true;
+        state.items.clear();
+        self.not_full.notify_one();
+        if state.job == JobState::Running {
+            interrupt.interrupt();
+        }
+    }
+}
+
+// Sending side of a `Channel`, owned by a job.
+struct Producer<T> {
+    channel: Arc<Channel<T>>,
+    finished: bool,
+}
+
+impl<T> Producer<T> {
+    // Returns `false` if the job has been cancelled.
+    fn start(&self) -> bool {
+        let mut state = self.channel.lock();
+        if state.cancelled {
+            return false;
+        }
+        state.job = JobState::Running;
+        true
+    }
+
+    // Blocks while the channel is full, returns `false` if the receiver has
+    // been dropped.
+    fn push(&self, item: Result<T>) -> bool {
+        let mut state = self.channel.lock();
+        while !state.cancelled && state.items.len() >= self.channel.capacity {
+            state = self
+                .channel
+                .not_full
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        if state.cancelled {
+            return false;
+        }
+        state.items.push_back(item);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        true
+    }
+
+    fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl<T> Drop for Producer<T> {
+    fn drop(&mut self) {
+        let mut state = self.channel.lock();
+        state.job = JobState::Done;
+        state.aborted = !self.finished && !state.cancelled;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+fn worker_gone() -> crate::Error {
+    error_from_sqlite_code(
+        ffi::SQLITE_MISUSE,
+        Some("connection thread terminated".to_owned()),
+    )
+}
+
+/// Future returned by [`AsyncConnection::call`].
+///
+/// Dropping it cancels the closure, or interrupts it if it is running.
+#[must_use = "futures do nothing unless polled"]
+pub struct Call<T> {
+    channel: Arc<Channel<T>>,
+    interrupt: Arc<InterruptHandle>,
+}
+
+impl<T> Future for Call<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        self.channel
+            .poll_recv(cx)
+            .map(|result| result.unwrap_or_else(|| Err(worker_gone())))
+    }
+}
+
+impl<T> Drop for Call<T> {
+    fn drop(&mut self) {
+        self.channel.cancel(&self.interrupt);
+    }
+}
+
+impl<T> fmt::Debug for Call<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Call").finish()
+    }
+}
+
+/// Stream of rows returned by [`AsyncConnection::query_stream`].
+///
+/// Dropping it stops the query, interrupting it if needed.
+#[must_use = "streams do nothing unless polled"]
+pub struct RowStream<T> {
+    channel: Arc<Channel<T>>,
+    interrupt: Arc<InterruptHandle>,
+}
+
+impl<T> Stream for RowStream<T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<T>>> {
+        self.channel.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for RowStream<T> {
+    fn drop(&mut self) {
+        self.channel.cancel(&self.interrupt);
+    }
+}
+
+impl<T> fmt::Debug for RowStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowStream").finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use futures_executor::{block_on, block_on_stream};
+
+    use super::AsyncConnection;
+    use crate::{params_from_iter, Connection, Error, ErrorCode, Result};
+
+    fn conn() -> Result<AsyncConnection> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES (1), (2), (3);")?;
+        Ok(AsyncConnection::new(db))
+    }
+
+    #[test]
+    fn test_call() -> Result<()> {
+        let conn = conn()?;
+        let sum: i64 =
+            block_on(conn.call(|db| db.query_row("SELECT sum(x) FROM foo", [], |r| r.get(0))))?;
+        assert_eq!(6, sum);
+        let err = block_on(conn.call(|db| db.execute("INSERT INTO bar VALUES (1)", [])));
+        assert!(err.is_err());
+        block_on(conn.close())
+    }
+
+    #[test]
+    fn test_query_stream() -> Result<()> {
+        let conn = conn()?;
+        let stream =
+            conn.query_stream("SELECT x FROM foo WHERE x > ?", [1], |r| r.get::<_, i64>(0));
+        let rows = block_on_stream(stream).collect::<Result<Vec<_>>>()?;
+        assert_eq!(vec![2, 3], rows);
+
+        let stream = conn.query_stream(
+            "SELECT * FROM bar",
+            params_from_iter(Vec::<i64>::new()),
+            |r| r.get::<_, i64>(0),
+        );
+        let rows = block_on_stream(stream).collect::<Vec<_>>();
+        assert_eq!(1, rows.len());
+        assert!(rows[0].is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_interrupts() -> Result<()> {
+        let conn = conn()?;
+        let (started, wait_started) = mpsc::channel();
+        let call = conn.call(move |db| {
+            let mut stmt = db.prepare(
+                "WITH RECURSIVE c(x) AS (VALUES(1) UNION ALL SELECT x + 1 FROM c) SELECT x FROM c",
+            )?;
+            let mut rows = stmt.query([])?;
+            // interrupting a connection without any running statement is a
+            // no-op: signal once the query has started
+            rows.next()?;
+            started.send(()).unwrap();
+            while rows.next()?.is_some() {}
+            Ok(())
+        });
+        let check =
+            conn.call(|db| db.query_row("SELECT count(*) FROM foo", [], |r| r.get::<_, i64>(0)));
+        wait_started.recv_timeout(Duration::from_secs(10)).unwrap();
+        drop(call);
+        // the next closure can run: the infinite query has been interrupted
+        assert_eq!(3, block_on(check)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_worker_gone() -> Result<()> {
+        let conn = conn()?;
+        let interrupted = conn.call(|_| -> Result<()> { panic!("boom") });
+        match block_on(interrupted) {
+            Err(Error::SqliteFailure(err, _)) => assert_eq!(ErrorCode::ApiMisuse, err.code),
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert!(block_on(conn.call(|_| Ok(()))).is_err());
+        Ok(())
+    }
+}
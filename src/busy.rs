@@ -1,10 +1,33 @@
-///! Busy handler (when the database is locked)
+//! Busy handler (when the database is locked)
+//!
+//! Besides [`Connection::busy_timeout`], this module provides ready-made
+//! busy handlers to be registered with [`Connection::set_busy_handler`].
+//! They are plain closures, so they can be wrapped to collect metrics or log
+//! contention:
+//!
+//! ```rust,no_run
+//! # use rusqlite::{busy, Connection, Result};
+//! # use std::time::Duration;
+//! fn configure(conn: &Connection) -> Result<()> {
+//!     let mut backoff = busy::exponential_backoff(
+//!         Duration::from_millis(1),
+//!         Duration::from_millis(100),
+//!         Duration::from_secs(5),
+//!     );
+//!     conn.set_busy_handler(Some(move |count| {
+//!         if count == 0 {
+//!             eprintln!("database is locked, backing off");
+//!         }
+//!         backoff(count)
+//!     }))
+//! }
+//! ```
 use std::convert::TryInto;
-use std::mem;
 use std::os::raw::{c_int, c_void};
 use std::panic::catch_unwind;
 use std::ptr;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::ffi;
 use crate::{Connection, InnerConnection, Result};
@@ -55,23 +78,24 @@ impl Connection {
     /// Newly created connections default to a
     /// [`busy_timeout()`](Connection::busy_timeout) handler with a timeout
     /// of 5000ms, although this is subject to change.
+    ///
+    /// See [`set_busy_handler`](Connection::set_busy_handler) to register a
+    /// closure.
     pub fn busy_handler(&self, callback: Option<fn(i32) -> bool>) -> Result<()> {
-        unsafe extern "C" fn busy_handler_callback(p_arg: *mut c_void, count: c_int) -> c_int {
-            let handler_fn: fn(i32) -> bool = mem::transmute(p_arg);
-            if let Ok(true) = catch_unwind(|| handler_fn(count)) {
-                1
-            } else {
-                0
-            }
-        }
-        let c = self.db.borrow_mut();
-        let r = match callback {
-            Some(f) => unsafe {
-                ffi::sqlite3_busy_handler(c.db(), Some(busy_handler_callback), f as *mut c_void)
-            },
-            None => unsafe { ffi::sqlite3_busy_handler(c.db(), None, ptr::null_mut()) },
-        };
-        c.decode_result(r)
+        self.db.borrow_mut().busy_handler(callback)
+    }
+
+    /// Register a closure to handle `SQLITE_BUSY` errors.
+    ///
+    /// This is like [`busy_handler`](Connection::busy_handler), but the
+    /// callback can capture state, e.g. to collect metrics or to implement a
+    /// backoff policy. See the [`busy`](crate::busy) module for ready-made
+    /// handlers.
+    pub fn set_busy_handler<F>(&self, callback: Option<F>) -> Result<()>
+    where
+        F: FnMut(i32) -> bool + Send + 'static,
+    {
+        self.db.borrow_mut().busy_handler(callback)
     }
 }
 
@@ -79,16 +103,137 @@ impl InnerConnection {
     #[inline]
     fn busy_timeout(&mut self, timeout: c_int) -> Result<()> {
         let r = unsafe { ffi::sqlite3_busy_timeout(self.db, timeout) };
+        // the previous busy handler has been replaced
+        self.busy_handler = None;
         self.decode_result(r)
     }
+
+    fn busy_handler<F>(&mut self, callback: Option<F>) -> Result<()>
+    where
+        F: FnMut(i32) -> bool + Send + 'static,
+    {
+        unsafe extern "C" fn call_boxed_closure<F>(p_arg: *mut c_void, count: c_int) -> c_int
+        where
+            F: FnMut(i32) -> bool,
+        {
+            let r = catch_unwind(|| {
+                let boxed_handler: *mut F = p_arg as *mut F;
+                (*boxed_handler)(count)
+            });
+            if let Ok(true) = r {
+                1
+            } else {
+                0
+            }
+        }
+
+        match callback {
+            Some(callback) => {
+                let mut boxed_handler = Box::new(callback);
+                let r = unsafe {
+                    ffi::sqlite3_busy_handler(
+                        self.db(),
+                        Some(call_boxed_closure::<F>),
+                        &mut *boxed_handler as *mut F as *mut _,
+                    )
+                };
+                self.busy_handler = Some(boxed_handler);
+                self.decode_result(r)
+            }
+            None => {
+                let r = unsafe { ffi::sqlite3_busy_handler(self.db(), None, ptr::null_mut()) };
+                self.busy_handler = None;
+                self.decode_result(r)
+            }
+        }
+    }
+}
+
+/// Busy handler which sleeps for exponentially growing delays, starting at
+/// `initial` and capped at `max_delay`, until `timeout` has elapsed since the
+/// beginning of the locking event.
+///
+/// Each delay is randomly shortened by up to half of its length so that
+/// connections waiting for the same lock do not retry in lockstep.
+pub fn exponential_backoff(
+    initial: Duration,
+    max_delay: Duration,
+    timeout: Duration,
+) -> impl FnMut(i32) -> bool + Send + 'static {
+    let mut start = Instant::now();
+    move |count| {
+        if count == 0 {
+            start = Instant::now();
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return false;
+        }
+        let delay = 1u32
+            .checked_shl(count as u32)
+            .and_then(|factor| initial.checked_mul(factor))
+            .map_or(max_delay, |delay| delay.min(max_delay));
+        let delay = jitter(delay).min(timeout - elapsed);
+        thread::sleep(delay);
+        true
+    }
+}
+
+/// Busy handler which retries every `interval` until `deadline`, whatever
+/// the number of locking events in between.
+///
+/// This is useful to bound the total time spent waiting for locks by a
+/// request which runs several statements.
+pub fn deadline(deadline: Instant, interval: Duration) -> impl FnMut(i32) -> bool + Send + 'static {
+    move |_| {
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        thread::sleep(interval.min(deadline - now));
+        true
+    }
+}
+
+/// Busy handler which yields the current thread instead of sleeping, at most
+/// `max_retries` times per locking event.
+///
+/// This gives the lock holder a chance to make progress without adding any
+/// latency, which suits short transactions on a busy multi-core machine.
+///
+/// Note that busy handlers are only called for `SQLITE_BUSY`: the
+/// `SQLITE_LOCKED` errors of a shared cache are handled by the
+/// `unlock_notify` feature instead.
+pub fn yield_thread(max_retries: i32) -> impl FnMut(i32) -> bool + Send + 'static {
+    move |count| {
+        if count >= max_retries {
+            return false;
+        }
+        thread::yield_now();
+        true
+    }
+}
+
+// Random duration in `[delay / 2, delay]`.
+fn jitter(delay: Duration) -> Duration {
+    let mut random: u32 = 0;
+    unsafe {
+        ffi::sqlite3_randomness(
+            std::mem::size_of::<u32>() as c_int,
+            &mut random as *mut u32 as *mut c_void,
+        )
+    };
+    let half = delay / 2;
+    half + half.mul_f64(f64::from(random) / f64::from(u32::MAX))
 }
 
 #[cfg(test)]
 mod test {
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     use std::sync::mpsc::sync_channel;
+    use std::sync::Arc;
     use std::thread;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     use crate::{Connection, Error, ErrorCode, Result, TransactionBehavior};
 
@@ -110,6 +255,61 @@ mod test {
         tx1.rollback()
     }
 
+    #[test]
+    fn test_busy_handler_closure() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db3");
+
+        let mut db1 = Connection::open(&path)?;
+        let tx1 = db1.transaction_with_behavior(TransactionBehavior::Exclusive)?;
+        let db2 = Connection::open(&path)?;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        db2.set_busy_handler(Some(move |count| {
+            handler_calls.fetch_add(1, Ordering::Relaxed);
+            count < 2
+        }))?;
+        let r: Result<()> = db2.query_row("PRAGMA schema_version", [], |_| unreachable!());
+        assert!(r.is_err());
+        assert_eq!(3, calls.load(Ordering::Relaxed));
+        tx1.rollback()
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let timeout = Duration::from_millis(20);
+        let mut handler =
+            super::exponential_backoff(Duration::from_millis(1), Duration::from_millis(4), timeout);
+        let start = Instant::now();
+        let mut count = 0;
+        while handler(count) {
+            count += 1;
+        }
+        assert!(start.elapsed() >= timeout);
+        assert!(count > 3);
+        // a new locking event restarts the timeout
+        assert!(handler(0));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let mut handler = super::deadline(
+            Instant::now() + Duration::from_millis(10),
+            Duration::from_millis(1),
+        );
+        assert!(handler(0));
+        thread::sleep(Duration::from_millis(10));
+        assert!(!handler(0));
+    }
+
+    #[test]
+    fn test_yield_thread() {
+        let mut handler = super::yield_thread(2);
+        assert!(handler(0));
+        assert!(handler(1));
+        assert!(!handler(2));
+    }
+
     #[test]
     #[ignore] // FIXME: unstable
     fn test_busy_timeout() {
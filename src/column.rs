@@ -1,5 +1,14 @@
 use std::str;
 
+#[cfg(feature = "column_metadata")]
+use std::ffi::CStr;
+#[cfg(feature = "column_metadata")]
+use std::os::raw::{c_char, c_int};
+#[cfg(feature = "column_metadata")]
+use std::ptr;
+
+#[cfg(feature = "column_metadata")]
+use crate::{ffi, str_to_cstring, Connection, DatabaseName};
 use crate::{Error, Result, Statement};
 
 /// Information about a column of a SQLite query.
@@ -7,6 +16,12 @@ use crate::{Error, Result, Statement};
 pub struct Column<'stmt> {
     name: &'stmt str,
     decl_type: Option<&'stmt str>,
+    #[cfg(feature = "column_metadata")]
+    database_name: Option<&'stmt str>,
+    #[cfg(feature = "column_metadata")]
+    table_name: Option<&'stmt str>,
+    #[cfg(feature = "column_metadata")]
+    origin_name: Option<&'stmt str>,
 }
 
 impl Column<'_> {
@@ -21,6 +36,51 @@ impl Column<'_> {
     pub fn decl_type(&self) -> Option<&str> {
         self.decl_type
     }
+
+    /// Returns the name of the database (e.g. `main`) of the table the column
+    /// comes from (`None` for an expression).
+    #[cfg(feature = "column_metadata")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "column_metadata")))]
+    #[inline]
+    pub fn database_name(&self) -> Option<&str> {
+        self.database_name
+    }
+
+    /// Returns the name of the table the column comes from (`None` for an
+    /// expression).
+    #[cfg(feature = "column_metadata")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "column_metadata")))]
+    #[inline]
+    pub fn table_name(&self) -> Option<&str> {
+        self.table_name
+    }
+
+    /// Returns the name of the table column the column comes from, whatever
+    /// its alias in the query (`None` for an expression).
+    #[cfg(feature = "column_metadata")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "column_metadata")))]
+    #[inline]
+    pub fn origin_name(&self) -> Option<&str> {
+        self.origin_name
+    }
+}
+
+/// Metadata of a table column, see [`Connection::table_column_metadata`].
+#[cfg(feature = "column_metadata")]
+#[cfg_attr(docsrs, doc(cfg(feature = "column_metadata")))]
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ColumnMetadata {
+    /// Declared type
+    pub decl_type: Option<String>,
+    /// Name of the default collation sequence
+    pub collation: Option<String>,
+    /// `true` if the column has a `NOT NULL` constraint
+    pub not_null: bool,
+    /// `true` if the column is part of the primary key
+    pub primary_key: bool,
+    /// `true` if the column is `AUTOINCREMENT`
+    pub auto_increment: bool,
 }
 
 impl Statement<'_> {
@@ -142,10 +202,122 @@ impl Statement<'_> {
             let decl_type = slice.map(|s| {
                 str::from_utf8(s.to_bytes()).expect("Invalid UTF-8 sequence in column declaration")
             });
-            cols.push(Column { name, decl_type });
+            cols.push(Column {
+                name,
+                decl_type,
+                #[cfg(feature = "column_metadata")]
+                database_name: self.stmt.column_database_name(i).map(utf8),
+                #[cfg(feature = "column_metadata")]
+                table_name: self.stmt.column_table_name(i).map(utf8),
+                #[cfg(feature = "column_metadata")]
+                origin_name: self.stmt.column_origin_name(i).map(utf8),
+            });
         }
         cols
     }
+
+    /// Returns the name of the database of the table the column `col` comes
+    /// from (`None` for an expression).
+    ///
+    /// ## Failure
+    ///
+    /// Returns an `Error::InvalidColumnIndex` if `col` is outside the valid
+    /// column range for this row.
+    #[cfg(feature = "column_metadata")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "column_metadata")))]
+    #[inline]
+    pub fn column_database_name(&self, col: usize) -> Result<Option<&str>> {
+        self.column_name(col)?;
+        Ok(self.stmt.column_database_name(col).map(utf8))
+    }
+
+    /// Returns the name of the table the column `col` comes from (`None` for
+    /// an expression).
+    ///
+    /// ## Failure
+    ///
+    /// Returns an `Error::InvalidColumnIndex` if `col` is outside the valid
+    /// column range for this row.
+    #[cfg(feature = "column_metadata")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "column_metadata")))]
+    #[inline]
+    pub fn column_table_name(&self, col: usize) -> Result<Option<&str>> {
+        self.column_name(col)?;
+        Ok(self.stmt.column_table_name(col).map(utf8))
+    }
+
+    /// Returns the name of the table column the column `col` comes from
+    /// (`None` for an expression).
+    ///
+    /// ## Failure
+    ///
+    /// Returns an `Error::InvalidColumnIndex` if `col` is outside the valid
+    /// column range for this row.
+    #[cfg(feature = "column_metadata")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "column_metadata")))]
+    #[inline]
+    pub fn column_origin_name(&self, col: usize) -> Result<Option<&str>> {
+        self.column_name(col)?;
+        Ok(self.stmt.column_origin_name(col).map(utf8))
+    }
+}
+
+#[cfg(feature = "column_metadata")]
+fn utf8(s: &CStr) -> &str {
+    str::from_utf8(s.to_bytes()).expect("Invalid UTF-8 sequence in column metadata")
+}
+
+#[cfg(feature = "column_metadata")]
+#[cfg_attr(docsrs, doc(cfg(feature = "column_metadata")))]
+impl Connection {
+    /// Returns the metadata of `column` in `table`, searching all the
+    /// databases if `db_name` is `None`.
+    ///
+    /// ## Failure
+    ///
+    /// Will return `Err` if the table or the column does not exist.
+    pub fn table_column_metadata(
+        &self,
+        db_name: Option<DatabaseName<'_>>,
+        table: &str,
+        column: &str,
+    ) -> Result<ColumnMetadata> {
+        let db_name = db_name.map(|n| n.as_cstring()).transpose()?;
+        let table = str_to_cstring(table)?;
+        let column = str_to_cstring(column)?;
+        let mut decl_type: *const c_char = ptr::null();
+        let mut collation: *const c_char = ptr::null();
+        let (mut not_null, mut primary_key, mut auto_increment): (c_int, c_int, c_int) = (0, 0, 0);
+        let c = self.db.borrow();
+        let r = unsafe {
+            ffi::sqlite3_table_column_metadata(
+                c.db(),
+                db_name.as_ref().map_or(ptr::null(), |n| n.as_ptr()),
+                table.as_ptr(),
+                column.as_ptr(),
+                &mut decl_type,
+                &mut collation,
+                &mut not_null,
+                &mut primary_key,
+                &mut auto_increment,
+            )
+        };
+        c.decode_result(r)?;
+        let owned = |p: *const c_char| {
+            if p.is_null() {
+                None
+            } else {
+                Some(unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned())
+            }
+        };
+        Ok(ColumnMetadata {
+            decl_type: owned(decl_type),
+            collation: owned(collation),
+            not_null: not_null != 0,
+            primary_key: primary_key != 0,
+            auto_increment: auto_increment != 0,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -173,6 +345,35 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    #[cfg(feature = "column_metadata")]
+    fn test_column_metadata() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE foo(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL COLLATE NOCASE);",
+        )?;
+        let stmt = db.prepare("SELECT name AS n, id + 1 FROM foo")?;
+        let columns = stmt.columns();
+        assert_eq!(Some("main"), columns[0].database_name());
+        assert_eq!(Some("foo"), columns[0].table_name());
+        assert_eq!(Some("name"), columns[0].origin_name());
+        assert_eq!(None, columns[1].table_name());
+        assert_eq!(Some("foo"), stmt.column_table_name(0)?);
+        assert_eq!(None, stmt.column_origin_name(1)?);
+        assert!(stmt.column_database_name(2).is_err());
+
+        let name = db.table_column_metadata(None, "foo", "name")?;
+        assert_eq!(Some("TEXT"), name.decl_type.as_deref());
+        assert_eq!(Some("NOCASE"), name.collation.as_deref());
+        assert!(name.not_null);
+        assert!(!name.primary_key);
+        let id = db.table_column_metadata(Some(crate::DatabaseName::Main), "foo", "id")?;
+        assert!(id.primary_key);
+        assert!(id.auto_increment);
+        assert!(db.table_column_metadata(None, "foo", "bar").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_column_name_in_error() -> Result<()> {
         use crate::{types::Type, Error};
@@ -128,6 +128,17 @@ pub enum Error {
     #[cfg(feature = "blob")]
     #[cfg_attr(docsrs, doc(cfg(feature = "blob")))]
     BlobSizeError,
+
+    /// Error returned by [`migrations::Migrations`](crate::migrations::Migrations)
+    /// when the requested or current version of the database is unknown, or
+    /// when a step to revert is not reversible.
+    MigrationError(String),
+
+    /// Error returned by the setters of [`Pragmas`](crate::pragmas::Pragmas)
+    /// when the value read back differs from the one set (e.g. `WAL` journal
+    /// mode on an in-memory database). The fields are the name of the pragma
+    /// and the value read back.
+    PragmaNotApplied(String, String),
 }
 
 impl PartialEq for Error {
@@ -172,6 +183,10 @@ impl PartialEq for Error {
             }
             #[cfg(feature = "blob")]
             (Error::BlobSizeError, Error::BlobSizeError) => true,
+            (Error::MigrationError(s1), Error::MigrationError(s2)) => s1 == s2,
+            (Error::PragmaNotApplied(n1, v1), Error::PragmaNotApplied(n2, v2)) => {
+                n1 == n2 && v1 == v2
+            }
             (..) => false,
         }
     }
@@ -191,7 +206,7 @@ impl From<::std::ffi::NulError> for Error {
     }
 }
 
-const UNKNOWN_COLUMN: usize = std::usize::MAX;
+pub(crate) const UNKNOWN_COLUMN: usize = std::usize::MAX;
 
 /// The conversion isn't precise, but it's convenient to have it
 /// to allow use of `get_raw(…).as_…()?` in callbacks that take `Error`.
@@ -289,6 +304,10 @@ impl fmt::Display for Error {
 
             #[cfg(feature = "blob")]
             Error::BlobSizeError => "Blob size is insufficient".fmt(f),
+            Error::MigrationError(ref desc) => write!(f, "{}", desc),
+            Error::PragmaNotApplied(ref name, ref value) => {
+                write!(f, "PRAGMA {} was not applied, its value is {}", name, value)
+            }
         }
     }
 }
@@ -312,7 +331,9 @@ impl error::Error for Error {
             | Error::InvalidParameterCount(..)
             | Error::StatementChangedRows(_)
             | Error::InvalidQuery
-            | Error::MultipleStatement => None,
+            | Error::MultipleStatement
+            | Error::MigrationError(_)
+            | Error::PragmaNotApplied(..) => None,
 
             #[cfg(feature = "functions")]
             Error::InvalidFunctionParameterType(..) => None,
@@ -0,0 +1,317 @@
+//! `EXPLAIN QUERY PLAN` and `EXPLAIN` output of a prepared statement
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result};
+//! fn check_index(conn: &Connection) -> Result<()> {
+//!     conn.execute_batch("CREATE TABLE foo(x INTEGER); CREATE INDEX foo_x ON foo(x);")?;
+//!     let stmt = conn.prepare("SELECT * FROM foo WHERE x = ?")?;
+//!     let plan = stmt.query_plan()?;
+//!     assert!(plan.uses_index("foo_x"));
+//!     assert!(!plan.has_full_scan("foo"));
+//!     println!("{}", plan);
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt;
+
+#[cfg(feature = "modern_sqlite")]
+use crate::error::error_from_sqlite_code;
+#[cfg(feature = "modern_sqlite")]
+use crate::ffi;
+use crate::types::ValueRef;
+use crate::{Result, Row, Statement};
+
+/// Query plan of a statement, as reported by `EXPLAIN QUERY PLAN`.
+///
+/// The `Display` implementation prints the plan like the `sqlite3` shell.
+///
+/// Note that SQLite versions older than 3.24.0 report a flat list of nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryPlan {
+    /// Top-level nodes of the plan
+    pub nodes: Vec<QueryPlanNode>,
+}
+
+/// One line of `EXPLAIN QUERY PLAN`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryPlanNode {
+    /// Node id
+    pub id: i64,
+    /// Id of the parent node, 0 for a top-level node
+    pub parent: i64,
+    /// Description of the step, e.g. `SCAN foo` or
+    /// `SEARCH foo USING INDEX foo_x (x=?)`
+    pub detail: String,
+    /// Children nodes
+    pub children: Vec<QueryPlanNode>,
+}
+
+impl QueryPlan {
+    fn from_rows(rows: Vec<(i64, i64, String)>) -> QueryPlan {
+        fn children(rows: &[(i64, i64, String)], parent: i64) -> Vec<QueryPlanNode> {
+            rows.iter()
+                // ids are always positive, so a node cannot be its own parent
+                .filter(|r| r.1 == parent && r.0 != parent)
+                .map(|(id, parent, detail)| QueryPlanNode {
+                    id: *id,
+                    parent: *parent,
+                    detail: detail.clone(),
+                    children: children(rows, *id),
+                })
+                .collect()
+        }
+        QueryPlan {
+            nodes: children(&rows, 0),
+        }
+    }
+
+    /// Visit all the nodes of the plan, depth first.
+    pub fn walk<F: FnMut(&QueryPlanNode)>(&self, mut f: F) {
+        fn walk<F: FnMut(&QueryPlanNode)>(nodes: &[QueryPlanNode], f: &mut F) {
+            for node in nodes {
+                f(node);
+                walk(&node.children, f);
+            }
+        }
+        walk(&self.nodes, &mut f);
+    }
+
+    fn any<F: Fn(&str) -> bool>(&self, f: F) -> bool {
+        let mut found = false;
+        self.walk(|node| found = found || f(&node.detail));
+        found
+    }
+
+    /// Returns `true` if the index `name` is used by at least one step of the
+    /// plan.
+    pub fn uses_index(&self, name: &str) -> bool {
+        self.any(|detail| {
+            ["USING INDEX ", "USING COVERING INDEX "]
+                .iter()
+                .any(|using| match detail.find(using) {
+                    Some(i) => first_word(&detail[i + using.len()..]) == name,
+                    None => false,
+                })
+        })
+    }
+
+    /// Returns `true` if the table `table` is fully scanned (possibly through
+    /// a covering index) by at least one step of the plan.
+    pub fn has_full_scan(&self, table: &str) -> bool {
+        self.any(|detail| match detail.strip_prefix("SCAN ") {
+            // before 3.36.0: "SCAN TABLE foo"
+            Some(rest) => {
+                first_word(rest) == table
+                    || rest.strip_prefix("TABLE ").map(first_word) == Some(table)
+            }
+            None => false,
+        })
+    }
+}
+
+fn first_word(s: &str) -> &str {
+    s.split(' ').next().unwrap_or_default()
+}
+
+impl fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_nodes(
+            f: &mut fmt::Formatter<'_>,
+            nodes: &[QueryPlanNode],
+            prefix: &str,
+        ) -> fmt::Result {
+            for (i, node) in nodes.iter().enumerate() {
+                let last = i == nodes.len() - 1;
+                writeln!(
+                    f,
+                    "{}{}{}",
+                    prefix,
+                    if last { "`--" } else { "|--" },
+                    node.detail
+                )?;
+                let prefix = format!("{}{}", prefix, if last { "   " } else { "|  " });
+                write_nodes(f, &node.children, &prefix)?;
+            }
+            Ok(())
+        }
+        writeln!(f, "QUERY PLAN")?;
+        write_nodes(f, &self.nodes, "")
+    }
+}
+
+/// One instruction of the virtual machine program, as reported by `EXPLAIN`.
+///
+/// See <https://sqlite.org/opcode.html> for the meaning of each operand.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    /// Address of the instruction
+    pub addr: i64,
+    /// Name of the opcode
+    pub opcode: String,
+    /// First operand
+    pub p1: i64,
+    /// Second operand
+    pub p2: i64,
+    /// Third operand
+    pub p3: i64,
+    /// Fourth operand
+    pub p4: Option<String>,
+    /// Fifth operand
+    pub p5: i64,
+    /// Comment (only available if SQLite is compiled with
+    /// `SQLITE_ENABLE_EXPLAIN_COMMENTS`)
+    pub comment: Option<String>,
+}
+
+impl Instruction {
+    fn from_row(row: &Row<'_>) -> Result<Instruction> {
+        Ok(Instruction {
+            addr: row.get(0)?,
+            opcode: row.get(1)?,
+            p1: row.get(2)?,
+            p2: row.get(3)?,
+            p3: row.get(4)?,
+            p4: optional_text(row.get_ref(5)?),
+            p5: row.get(6)?,
+            comment: optional_text(row.get_ref(7)?),
+        })
+    }
+}
+
+fn optional_text(value: ValueRef<'_>) -> Option<String> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(i) => Some(i.to_string()),
+        ValueRef::Real(f) => Some(f.to_string()),
+        ValueRef::Text(s) | ValueRef::Blob(s) => Some(String::from_utf8_lossy(s).into_owned()),
+    }
+}
+
+impl Statement<'_> {
+    /// Returns the query plan of this statement.
+    ///
+    /// The parameters currently bound to this statement are used, so that
+    /// the plan is the one SQLite would choose when executing it with the
+    /// same values. This statement is left untouched.
+    ///
+    /// Without the `modern_sqlite` feature (`sqlite3_expanded_sql`), the
+    /// parameters are not available and the plan is computed as if they were
+    /// all `NULL`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the `EXPLAIN QUERY PLAN` statement cannot be
+    /// prepared or executed.
+    pub fn query_plan(&self) -> Result<QueryPlan> {
+        self.explain("EXPLAIN QUERY PLAN ", |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(3)?))
+        })
+        .map(QueryPlan::from_rows)
+    }
+
+    /// Returns the virtual machine program of this statement.
+    ///
+    /// The parameters currently bound to this statement are used, see
+    /// [`Statement::query_plan`]: they appear as constants in the program.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the `EXPLAIN` statement cannot be prepared or
+    /// executed.
+    pub fn bytecode(&self) -> Result<Vec<Instruction>> {
+        self.explain("EXPLAIN ", Instruction::from_row)
+    }
+
+    fn explain<T, F>(&self, prefix: &str, f: F) -> Result<Vec<T>>
+    where
+        F: FnMut(&Row<'_>) -> Result<T>,
+    {
+        if self.stmt.sql().is_none() {
+            return Ok(Vec::new());
+        }
+        // SQL text with the bound parameters expanded as literals: the
+        // bindings themselves cannot be read back from SQLite.
+        #[cfg(feature = "modern_sqlite")]
+        let sql = match self.stmt.expanded_sql() {
+            Some(sql) => sql,
+            // too long or out of memory
+            None => return Err(error_from_sqlite_code(ffi::SQLITE_NOMEM, None)),
+        };
+        #[cfg(feature = "modern_sqlite")]
+        let sql = sql.as_cstr();
+        #[cfg(not(feature = "modern_sqlite"))]
+        let sql = self.stmt.sql().unwrap();
+        let mut explain = self.conn.prepare(&format!("{}{}", prefix, sql.to_str()?))?;
+        let result = explain.raw_query().mapped(f).collect();
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Connection, Result};
+
+    #[test]
+    fn test_query_plan() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE foo(x INTEGER, y TEXT); CREATE INDEX foo_x ON foo(x);
+             CREATE TABLE bar(y TEXT);",
+        )?;
+        let stmt = db.prepare("SELECT * FROM foo WHERE x = ?")?;
+        let plan = stmt.query_plan()?;
+        assert!(plan.uses_index("foo_x"));
+        assert!(!plan.uses_index("foo"));
+        assert!(!plan.has_full_scan("foo"));
+
+        let stmt = db.prepare("SELECT * FROM foo WHERE y IN (SELECT y FROM bar) ORDER BY y")?;
+        let plan = stmt.query_plan()?;
+        assert!(plan.has_full_scan("foo"));
+        assert!(plan.has_full_scan("bar"));
+        assert!(!plan.uses_index("foo_x"));
+        let printed = plan.to_string();
+        assert!(printed.starts_with("QUERY PLAN\n"));
+        assert!(printed.contains("`--"));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "modern_sqlite")]
+    fn test_query_plan_params() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE foo(x INTEGER, y TEXT); CREATE INDEX foo_a ON foo(x) WHERE y = 'a';",
+        )?;
+        let mut stmt = db.prepare("SELECT * FROM foo WHERE y = ? AND x = ?")?;
+        // the partial index can only be used when y is 'a'
+        stmt.raw_bind_parameter(1, "a")?;
+        stmt.raw_bind_parameter(2, 42)?;
+        assert!(stmt.query_plan()?.uses_index("foo_a"));
+        assert!(stmt
+            .bytecode()?
+            .iter()
+            .any(|i| i.opcode == "Integer" && i.p1 == 42));
+        stmt.raw_bind_parameter(1, "b")?;
+        assert!(!stmt.query_plan()?.uses_index("foo_a"));
+        // the bindings are left untouched
+        assert!(stmt.raw_query().next()?.is_none());
+        assert_eq!(
+            Some("SELECT * FROM foo WHERE y = 'b' AND x = 42".to_owned()),
+            stmt.expanded_sql()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecode() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let stmt = db.prepare("SELECT 1")?;
+        let program = stmt.bytecode()?;
+        assert_eq!(0, program[0].addr);
+        assert_eq!("Init", program[0].opcode);
+        assert!(program.iter().any(|i| i.opcode == "ResultRow"));
+        assert!(program.iter().any(|i| i.opcode == "Halt"));
+        Ok(())
+    }
+}
@@ -1,13 +1,19 @@
-//! Commit, Data Change and Rollback Notification Callbacks
+//! Commit, Data Change, Pre-Update, Rollback and WAL Notification Callbacks
 #![allow(non_camel_case_types)]
 
+#[cfg(feature = "preupdate_hook")]
+use std::marker::PhantomData;
 use std::os::raw::{c_char, c_int, c_void};
 use std::panic::{catch_unwind, RefUnwindSafe};
 use std::ptr;
 
+#[cfg(feature = "preupdate_hook")]
+use crate::error::error_from_sqlite_code;
 use crate::ffi;
+#[cfg(feature = "preupdate_hook")]
+use crate::types::ValueRef;
 
-use crate::{Connection, InnerConnection};
+use crate::{Connection, DatabaseName, InnerConnection, Result};
 
 /// Action Codes
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -339,6 +345,111 @@ impl Authorization {
     }
 }
 
+/// Checkpoint mode of [`wal_checkpoint`](Connection::wal_checkpoint).
+///
+/// See <https://sqlite.org/c3ref/wal_checkpoint_v2.html> for more info.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum CheckpointMode {
+    /// Checkpoint as many frames as possible without waiting for any
+    /// database readers or writers to finish.
+    Passive,
+    /// Wait until there is no writer and all readers are reading from the
+    /// most recent snapshot, then checkpoint all frames.
+    Full,
+    /// Like `Full`, then wait until all readers are done with the WAL file.
+    Restart,
+    /// Like `Restart`, then truncate the WAL file to zero bytes.
+    #[cfg(feature = "modern_sqlite")] // 3.8.8
+    #[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+    Truncate,
+}
+
+impl CheckpointMode {
+    fn into_raw(self) -> c_int {
+        match self {
+            Self::Passive => ffi::SQLITE_CHECKPOINT_PASSIVE,
+            Self::Full => ffi::SQLITE_CHECKPOINT_FULL,
+            Self::Restart => ffi::SQLITE_CHECKPOINT_RESTART,
+            #[cfg(feature = "modern_sqlite")]
+            Self::Truncate => ffi::SQLITE_CHECKPOINT_TRUNCATE,
+        }
+    }
+}
+
+/// Access to the row being changed, given to a
+/// [`preupdate_hook`](Connection::preupdate_hook).
+///
+/// See <https://sqlite.org/c3ref/preupdate_blobwrite.html> for more info.
+#[cfg(feature = "preupdate_hook")]
+#[cfg_attr(docsrs, doc(cfg(feature = "preupdate_hook")))]
+pub struct PreUpdateCase<'c> {
+    db: *mut ffi::sqlite3,
+    phantom: PhantomData<&'c ()>,
+}
+
+#[cfg(feature = "preupdate_hook")]
+impl PreUpdateCase<'_> {
+    /// Value of the `i`th column of the row before the change.
+    ///
+    /// Only available for `SQLITE_UPDATE` and `SQLITE_DELETE`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` for an `SQLITE_INSERT` or if `i` is out of range.
+    pub fn old_value(&self, i: usize) -> Result<ValueRef<'_>> {
+        let mut value = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_preupdate_old(self.db, i as c_int, &mut value) };
+        self.value(rc, value)
+    }
+
+    /// Value of the `i`th column of the row after the change.
+    ///
+    /// Only available for `SQLITE_INSERT` and `SQLITE_UPDATE`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` for an `SQLITE_DELETE` or if `i` is out of range.
+    pub fn new_value(&self, i: usize) -> Result<ValueRef<'_>> {
+        let mut value = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_preupdate_new(self.db, i as c_int, &mut value) };
+        self.value(rc, value)
+    }
+
+    fn value(&self, rc: c_int, value: *mut ffi::sqlite3_value) -> Result<ValueRef<'_>> {
+        if rc != ffi::SQLITE_OK {
+            return Err(error_from_sqlite_code(rc, None));
+        }
+        Ok(unsafe { ValueRef::from_value(value) })
+    }
+
+    /// Number of columns in the row being changed.
+    #[inline]
+    pub fn count(&self) -> usize {
+        unsafe { ffi::sqlite3_preupdate_count(self.db) as usize }
+    }
+
+    /// 0 for a change made by a top-level statement, 1 for a change made by
+    /// a trigger fired by a top-level statement, and so on.
+    #[inline]
+    pub fn depth(&self) -> c_int {
+        unsafe { ffi::sqlite3_preupdate_depth(self.db) }
+    }
+
+    /// Index of the column being written by `sqlite3_blob_write` when the
+    /// `SQLITE_DELETE` is triggered by an incremental blob write, `None` for
+    /// a regular change.
+    #[inline]
+    pub fn blobwrite(&self) -> Option<usize> {
+        let i = unsafe { ffi::sqlite3_preupdate_blobwrite(self.db) };
+        if i < 0 {
+            None
+        } else {
+            Some(i as usize)
+        }
+    }
+}
+
 impl Connection {
     /// Register a callback function to be invoked whenever
     /// a transaction is committed.
@@ -406,6 +517,93 @@ impl Connection {
     {
         self.db.borrow_mut().authorizer(hook)
     }
+
+    /// Register a callback function to be invoked each time data is committed
+    /// to a database in WAL mode.
+    ///
+    /// The callback parameters are:
+    ///
+    /// - the name of the database ("main", "temp", ...),
+    /// - the number of pages currently in the write-ahead log file.
+    ///
+    /// An error returned by the callback is reported by the statement which
+    /// triggered the commit (the data is committed anyway).
+    ///
+    /// Note that this replaces the default hook installed by
+    /// `PRAGMA wal_autocheckpoint` / `sqlite3_wal_autocheckpoint`, and that
+    /// removing the hook with `None` does not restore it: run
+    /// `PRAGMA wal_autocheckpoint = N` again to re-enable automatic
+    /// checkpoints.
+    #[inline]
+    pub fn wal_hook<'c, F>(&'c self, hook: Option<F>)
+    where
+        F: FnMut(&str, c_int) -> Result<()> + Send + 'c,
+    {
+        self.db.borrow_mut().wal_hook(hook);
+    }
+
+    /// Register a callback function to be invoked before each row is
+    /// updated, inserted or deleted, in rowid and `WITHOUT ROWID` tables.
+    ///
+    /// The callback parameters are:
+    ///
+    /// - the type of database update (SQLITE_INSERT, SQLITE_UPDATE or
+    ///   SQLITE_DELETE),
+    /// - the name of the database ("main", "temp", ...),
+    /// - the name of the table that is updated,
+    /// - the ROWID of the row before the change (undefined for an INSERT),
+    /// - the ROWID of the row after the change (undefined for a DELETE),
+    /// - a [`PreUpdateCase`] giving access to the old and new column values.
+    ///
+    /// Rowids are undefined for `WITHOUT ROWID` tables.
+    ///
+    /// # Failure
+    ///
+    /// The session extension records changes through the same (single)
+    /// preupdate hook, so this will return `Err` while a
+    /// [`Session`](crate::session::Session) is attached to the connection.
+    /// Conversely, a session cannot be created while a preupdate hook is
+    /// registered.
+    #[cfg(feature = "preupdate_hook")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "preupdate_hook")))]
+    #[inline]
+    pub fn preupdate_hook<'c, F>(&'c self, hook: Option<F>) -> Result<()>
+    where
+        F: FnMut(Action, &str, &str, i64, i64, &PreUpdateCase<'_>) + Send + 'c,
+    {
+        self.db.borrow_mut().preupdate_hook(hook)
+    }
+
+    /// Checkpoint the `db_name` database (which must be in WAL mode).
+    ///
+    /// Returns the size of the write-ahead log in frames and the total
+    /// number of checkpointed frames (both are -1 if the database is not in
+    /// WAL mode).
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the underlying SQLite call fails, and notably
+    /// with `SQLITE_BUSY` if the checkpoint could not run to completion.
+    pub fn wal_checkpoint(
+        &self,
+        db_name: DatabaseName<'_>,
+        mode: CheckpointMode,
+    ) -> Result<(c_int, c_int)> {
+        let db_name = db_name.as_cstring()?;
+        let c = self.db.borrow();
+        let mut log_size = 0;
+        let mut checkpointed = 0;
+        let rc = unsafe {
+            ffi::sqlite3_wal_checkpoint_v2(
+                c.db(),
+                db_name.as_ptr(),
+                mode.into_raw(),
+                &mut log_size,
+                &mut checkpointed,
+            )
+        };
+        c.decode_result(rc).map(|_| (log_size, checkpointed))
+    }
 }
 
 impl InnerConnection {
@@ -416,6 +614,9 @@ impl InnerConnection {
         self.rollback_hook(None::<fn()>);
         self.progress_handler(0, None::<fn() -> bool>);
         self.authorizer(None::<fn(AuthContext<'_>) -> Authorization>);
+        self.wal_hook(None::<fn(&str, c_int) -> Result<()>>);
+        #[cfg(feature = "preupdate_hook")]
+        let _ = self.preupdate_hook(None::<fn(Action, &str, &str, i64, i64, &PreUpdateCase<'_>)>);
     }
 
     fn commit_hook<'c, F>(&'c mut self, hook: Option<F>)
@@ -667,6 +868,133 @@ impl InnerConnection {
     }
 }
 
+impl InnerConnection {
+    fn wal_hook<'c, F>(&'c mut self, hook: Option<F>)
+    where
+        F: FnMut(&str, c_int) -> Result<()> + Send + 'c,
+    {
+        unsafe extern "C" fn call_boxed_closure<F>(
+            p_arg: *mut c_void,
+            _: *mut ffi::sqlite3,
+            p_db_name: *const c_char,
+            pages: c_int,
+        ) -> c_int
+        where
+            F: FnMut(&str, c_int) -> Result<()>,
+        {
+            let r = catch_unwind(|| {
+                let boxed_hook: *mut F = p_arg as *mut F;
+                (*boxed_hook)(expect_utf8(p_db_name, "database name"), pages)
+            });
+            match r {
+                Ok(Ok(())) => ffi::SQLITE_OK,
+                Ok(Err(crate::Error::SqliteFailure(err, _))) => err.extended_code,
+                _ => ffi::SQLITE_ERROR,
+            }
+        }
+
+        let free_wal_hook = if hook.is_some() {
+            Some(free_boxed_hook::<F> as unsafe fn(*mut c_void))
+        } else {
+            None
+        };
+
+        let previous_hook = match hook {
+            Some(hook) => {
+                let boxed_hook: *mut F = Box::into_raw(Box::new(hook));
+                unsafe {
+                    ffi::sqlite3_wal_hook(
+                        self.db(),
+                        Some(call_boxed_closure::<F>),
+                        boxed_hook as *mut _,
+                    )
+                }
+            }
+            _ => unsafe { ffi::sqlite3_wal_hook(self.db(), None, ptr::null_mut()) },
+        };
+        if !previous_hook.is_null() {
+            if let Some(free_boxed_hook) = self.free_wal_hook {
+                unsafe { free_boxed_hook(previous_hook) };
+            }
+        }
+        self.free_wal_hook = free_wal_hook;
+    }
+}
+
+#[cfg(feature = "preupdate_hook")]
+impl InnerConnection {
+    fn preupdate_hook<'c, F>(&'c mut self, hook: Option<F>) -> Result<()>
+    where
+        F: FnMut(Action, &str, &str, i64, i64, &PreUpdateCase<'_>) + Send + 'c,
+    {
+        // `sqlite3session_create` chains the sessions through the pArg of the
+        // preupdate hook: replacing it would corrupt them.
+        #[cfg(feature = "session")]
+        if self.sessions.get() > 0 {
+            return Err(crate::Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_MISUSE),
+                Some("preupdate hook is in use by a session".to_owned()),
+            ));
+        }
+
+        unsafe extern "C" fn call_boxed_closure<F>(
+            p_arg: *mut c_void,
+            db: *mut ffi::sqlite3,
+            action_code: c_int,
+            p_db_name: *const c_char,
+            p_table_name: *const c_char,
+            old_row_id: i64,
+            new_row_id: i64,
+        ) where
+            F: FnMut(Action, &str, &str, i64, i64, &PreUpdateCase<'_>),
+        {
+            let action = Action::from(action_code);
+            let case = PreUpdateCase {
+                db,
+                phantom: PhantomData,
+            };
+            let _ = catch_unwind(|| {
+                let boxed_hook: *mut F = p_arg as *mut F;
+                (*boxed_hook)(
+                    action,
+                    expect_utf8(p_db_name, "database name"),
+                    expect_utf8(p_table_name, "table name"),
+                    old_row_id,
+                    new_row_id,
+                    &case,
+                );
+            });
+        }
+
+        let free_preupdate_hook = if hook.is_some() {
+            Some(free_boxed_hook::<F> as unsafe fn(*mut c_void))
+        } else {
+            None
+        };
+
+        let previous_hook = match hook {
+            Some(hook) => {
+                let boxed_hook: *mut F = Box::into_raw(Box::new(hook));
+                unsafe {
+                    ffi::sqlite3_preupdate_hook(
+                        self.db(),
+                        Some(call_boxed_closure::<F>),
+                        boxed_hook as *mut _,
+                    )
+                }
+            }
+            _ => unsafe { ffi::sqlite3_preupdate_hook(self.db(), None, ptr::null_mut()) },
+        };
+        if !previous_hook.is_null() {
+            if let Some(free_boxed_hook) = self.free_preupdate_hook {
+                unsafe { free_boxed_hook(previous_hook) };
+            }
+        }
+        self.free_preupdate_hook = free_preupdate_hook;
+        Ok(())
+    }
+}
+
 unsafe fn free_boxed_hook<F>(p: *mut c_void) {
     drop(Box::from_raw(p as *mut F));
 }
@@ -690,8 +1018,9 @@ unsafe fn expect_optional_utf8<'a>(
 
 #[cfg(test)]
 mod test {
-    use super::Action;
-    use crate::{Connection, Result};
+    use super::{Action, CheckpointMode};
+    use crate::{Connection, DatabaseName, Result};
+    use std::os::raw::c_int;
     use std::sync::atomic::{AtomicBool, Ordering};
 
     #[test]
@@ -753,6 +1082,84 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_wal_hook() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Connection::open(temp_dir.path().join("test.db3"))?;
+        let mode: String = db.query_row("PRAGMA journal_mode=WAL", [], |r| r.get(0))?;
+        assert_eq!("wal", mode);
+
+        let mut pages = 0;
+        db.wal_hook(Some(|db_name: &str, n| {
+            assert_eq!("main", db_name);
+            pages = n;
+            Ok(())
+        }));
+        db.execute_batch("CREATE TABLE foo (t TEXT); INSERT INTO foo VALUES ('lisa');")?;
+        db.wal_hook(None::<fn(&str, c_int) -> Result<()>>);
+        assert!(pages > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wal_checkpoint() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Connection::open(temp_dir.path().join("test.db3"))?;
+        // not in WAL mode
+        assert_eq!(
+            (-1, -1),
+            db.wal_checkpoint(DatabaseName::Main, CheckpointMode::Passive)?
+        );
+
+        db.query_row("PRAGMA journal_mode=WAL", [], |_| Ok(()))?;
+        db.query_row("PRAGMA wal_autocheckpoint=0", [], |_| Ok(()))?;
+        db.execute_batch("CREATE TABLE foo (t TEXT);")?;
+        let (log, checkpointed) = db.wal_checkpoint(DatabaseName::Main, CheckpointMode::Full)?;
+        assert!(log > 0);
+        assert_eq!(log, checkpointed);
+        #[cfg(feature = "modern_sqlite")]
+        assert_eq!(
+            (0, 0),
+            db.wal_checkpoint(DatabaseName::Main, CheckpointMode::Truncate)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "preupdate_hook")]
+    fn test_preupdate_hook() -> Result<()> {
+        use crate::types::ValueRef;
+
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo (t TEXT); INSERT INTO foo VALUES ('lisa');")?;
+
+        let mut called = false;
+        db.preupdate_hook(Some(
+            |action,
+             db: &str,
+             tbl: &str,
+             old_row_id,
+             new_row_id,
+             case: &super::PreUpdateCase<'_>| {
+                assert_eq!(Action::SQLITE_UPDATE, action);
+                assert_eq!("main", db);
+                assert_eq!("foo", tbl);
+                assert_eq!(1, old_row_id);
+                assert_eq!(1, new_row_id);
+                assert_eq!(1, case.count());
+                assert_eq!(0, case.depth());
+                assert_eq!(None, case.blobwrite());
+                assert_eq!(ValueRef::Text(b"lisa"), case.old_value(0).unwrap());
+                assert_eq!(ValueRef::Text(b"bart"), case.new_value(0).unwrap());
+                assert!(case.old_value(1).is_err());
+                called = true;
+            },
+        ))?;
+        db.execute_batch("UPDATE foo SET t = 'bart'")?;
+        assert!(called);
+        Ok(())
+    }
+
     #[test]
     fn test_progress_handler() -> Result<()> {
         let db = Connection::open_in_memory()?;
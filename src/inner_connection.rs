@@ -15,6 +15,8 @@ use crate::raw_statement::RawStatement;
 use crate::statement::Statement;
 use crate::unlock_notify;
 use crate::version::version_number;
+#[cfg(feature = "modern_sqlite")]
+use crate::{DatabaseName, TransactionState};
 
 pub struct InnerConnection {
     pub db: *mut ffi::sqlite3,
@@ -32,9 +34,20 @@ pub struct InnerConnection {
     #[cfg(feature = "hooks")]
     pub free_update_hook: Option<unsafe fn(*mut ::std::os::raw::c_void)>,
     #[cfg(feature = "hooks")]
+    pub free_wal_hook: Option<unsafe fn(*mut ::std::os::raw::c_void)>,
+    #[cfg(feature = "preupdate_hook")]
+    pub free_preupdate_hook: Option<unsafe fn(*mut ::std::os::raw::c_void)>,
+    // number of live sessions, which own the preupdate hook
+    #[cfg(all(feature = "preupdate_hook", feature = "session"))]
+    pub sessions: std::cell::Cell<usize>,
+    #[cfg(feature = "hooks")]
     pub progress_handler: Option<Box<dyn FnMut() -> bool + Send>>,
     #[cfg(feature = "hooks")]
     pub authorizer: Option<crate::hooks::BoxedAuthorizer>,
+    pub busy_handler: Option<Box<dyn FnMut(i32) -> bool + Send>>,
+    // boxed `trace_v2` callback and the function to free it
+    #[cfg(all(feature = "trace", feature = "modern_sqlite"))]
+    pub tracer: Option<crate::trace::BoxedTracer>,
     owned: bool,
 }
 
@@ -52,9 +65,18 @@ impl InnerConnection {
             #[cfg(feature = "hooks")]
             free_update_hook: None,
             #[cfg(feature = "hooks")]
+            free_wal_hook: None,
+            #[cfg(feature = "preupdate_hook")]
+            free_preupdate_hook: None,
+            #[cfg(all(feature = "preupdate_hook", feature = "session"))]
+            sessions: std::cell::Cell::new(0),
+            #[cfg(feature = "hooks")]
             progress_handler: None,
             #[cfg(feature = "hooks")]
             authorizer: None,
+            busy_handler: None,
+            #[cfg(all(feature = "trace", feature = "modern_sqlite"))]
+            tracer: None,
             owned,
         }
     }
@@ -161,10 +183,12 @@ impl InnerConnection {
             "Bug: Somehow interrupt_lock was cleared before the DB was closed"
         );
         if !self.owned {
+            drop(shared_handle);
+            self.remove_tracer();
             self.db = ptr::null_mut();
             return Ok(());
         }
-        unsafe {
+        let r = unsafe {
             let r = ffi::sqlite3_close(self.db);
             // Need to use _raw because _guard has a reference out, and
             // decode_result takes &mut self.
@@ -174,7 +198,13 @@ impl InnerConnection {
                 self.db = ptr::null_mut();
             }
             r
+        };
+        drop(shared_handle);
+        if r.is_ok() {
+            // The tracer may be notified of SQLITE_TRACE_CLOSE by sqlite3_close.
+            self.remove_tracer();
         }
+        r
     }
 
     #[inline]
@@ -287,6 +317,26 @@ impl InnerConnection {
         unsafe { ffi::sqlite3_get_autocommit(self.db()) != 0 }
     }
 
+    #[cfg(feature = "modern_sqlite")] // 3.34.0
+    pub fn txn_state(&self, db_name: Option<DatabaseName<'_>>) -> Result<TransactionState> {
+        let state = match db_name {
+            Some(db_name) => {
+                let db_name = db_name.as_cstring()?;
+                unsafe { ffi::sqlite3_txn_state(self.db, db_name.as_ptr()) }
+            }
+            None => unsafe { ffi::sqlite3_txn_state(self.db, ptr::null()) },
+        };
+        match state {
+            ffi::SQLITE_TXN_NONE => Ok(TransactionState::None),
+            ffi::SQLITE_TXN_READ => Ok(TransactionState::Read),
+            ffi::SQLITE_TXN_WRITE => Ok(TransactionState::Write),
+            _ => Err(error_from_sqlite_code(
+                ffi::SQLITE_MISUSE,
+                Some("unknown database".to_owned()),
+            )),
+        }
+    }
+
     #[cfg(feature = "modern_sqlite")] // 3.8.6
     pub fn is_busy(&self) -> bool {
         let db = self.db();
@@ -311,6 +361,10 @@ impl InnerConnection {
     #[cfg(not(feature = "hooks"))]
     #[inline]
     fn remove_hooks(&mut self) {}
+
+    #[cfg(not(all(feature = "trace", feature = "modern_sqlite")))]
+    #[inline]
+    fn remove_tracer(&mut self) {}
 }
 
 impl Drop for InnerConnection {
@@ -72,27 +72,46 @@ use crate::types::ValueRef;
 
 pub use crate::cache::CachedStatement;
 pub use crate::column::Column;
+#[cfg(feature = "column_metadata")]
+pub use crate::column::ColumnMetadata;
 pub use crate::error::Error;
 pub use crate::ffi::ErrorCode;
 #[cfg(feature = "load_extension")]
 pub use crate::load_extension_guard::LoadExtensionGuard;
-pub use crate::params::{params_from_iter, Params, ParamsFromIter};
+pub use crate::owned_statement::{OwnedMappedRows, OwnedRows, OwnedStatement};
+pub use crate::params::{params_from_iter, Params, ParamsFromIter, ToParams};
 pub use crate::row::{AndThenRows, Map, MappedRows, Row, RowIndex, Rows};
+#[cfg(feature = "scanstatus")]
+pub use crate::statement::ScanStatus;
 pub use crate::statement::{Statement, StatementStatus};
+pub use crate::status::{status, DbStatus, Status};
+#[cfg(feature = "modern_sqlite")]
+pub use crate::transaction::TransactionState;
 pub use crate::transaction::{DropBehavior, Savepoint, Transaction, TransactionBehavior};
 pub use crate::types::ToSql;
 pub use crate::version::*;
+#[cfg(feature = "query_macro")]
+#[cfg_attr(docsrs, doc(cfg(feature = "query_macro")))]
+pub use rusqlite_macros::query;
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use rusqlite_macros::FromRow;
 
 #[macro_use]
 mod error;
 
+pub mod appender;
+
+#[cfg(feature = "async_connection")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async_connection")))]
+pub mod async_connection;
 #[cfg(feature = "backup")]
 #[cfg_attr(docsrs, doc(cfg(feature = "backup")))]
 pub mod backup;
 #[cfg(feature = "blob")]
 #[cfg_attr(docsrs, doc(cfg(feature = "blob")))]
 pub mod blob;
-mod busy;
+pub mod busy;
 mod cache;
 #[cfg(feature = "collation")]
 #[cfg_attr(docsrs, doc(cfg(feature = "collation")))]
@@ -101,6 +120,7 @@ mod column;
 pub mod config;
 #[cfg(any(feature = "functions", feature = "vtab"))]
 mod context;
+pub mod explain;
 #[cfg(feature = "functions")]
 #[cfg_attr(docsrs, doc(cfg(feature = "functions")))]
 pub mod functions;
@@ -113,14 +133,32 @@ mod inner_connection;
 pub mod limits;
 #[cfg(feature = "load_extension")]
 mod load_extension_guard;
-mod params;
+pub mod migrations;
+pub mod options;
+mod owned_statement;
+pub mod params;
+#[cfg(feature = "pool")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pool")))]
+pub mod pool;
 mod pragma;
+pub mod pragmas;
+#[cfg(feature = "query_macro")]
+#[cfg_attr(docsrs, doc(cfg(feature = "query_macro")))]
+pub mod query;
 mod raw_statement;
 mod row;
+pub mod schema;
+#[cfg(feature = "serialize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serialize")))]
+pub mod serialize;
 #[cfg(feature = "session")]
 #[cfg_attr(docsrs, doc(cfg(feature = "session")))]
 pub mod session;
+#[cfg(feature = "snapshot")]
+#[cfg_attr(docsrs, doc(cfg(feature = "snapshot")))]
+pub mod snapshot;
 mod statement;
+mod status;
 #[cfg(feature = "trace")]
 #[cfg_attr(docsrs, doc(cfg(feature = "trace")))]
 pub mod trace;
@@ -128,6 +166,9 @@ mod transaction;
 pub mod types;
 mod unlock_notify;
 mod version;
+#[cfg(feature = "vfs")]
+#[cfg_attr(docsrs, doc(cfg(feature = "vfs")))]
+pub mod vfs;
 #[cfg(feature = "vtab")]
 #[cfg_attr(docsrs, doc(cfg(feature = "vtab")))]
 pub mod vtab;
@@ -313,6 +354,8 @@ pub const TEMP_DB: DatabaseName<'static> = DatabaseName::Temp;
 #[cfg(any(
     feature = "backup",
     feature = "blob",
+    feature = "column_metadata",
+    feature = "hooks",
     feature = "session",
     feature = "modern_sqlite"
 ))]
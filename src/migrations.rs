@@ -0,0 +1,352 @@
+//! Schema migrations tracked with `PRAGMA user_version`
+//!
+//! The version of a database is the number of steps applied to it, stored in
+//! its [`user_version`](https://sqlite.org/pragma.html#pragma_user_version).
+//! Each step is applied in its own transaction, together with the update of
+//! the version, and `PRAGMA foreign_key_check` must pass before the
+//! transaction is committed.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result};
+//! use rusqlite::migrations::{Migrations, Step};
+//!
+//! fn migrate(conn: &mut Connection) -> Result<()> {
+//!     let migrations = Migrations::new(vec![
+//!         Step::up("CREATE TABLE person(id INTEGER PRIMARY KEY, name TEXT NOT NULL);")
+//!             .down("DROP TABLE person;"),
+//!         Step::up_with(|tx| {
+//!             tx.execute("ALTER TABLE person ADD COLUMN email TEXT", [])?;
+//!             tx.execute("UPDATE person SET email = lower(name) || '@example.com'", [])?;
+//!             Ok(())
+//!         }),
+//!     ]);
+//!     migrations.to_latest(conn)?;
+//!     assert_eq!(2, migrations.current_version(conn)?);
+//!     Ok(())
+//! }
+//! # fn main() { migrate(&mut Connection::open_in_memory().unwrap()).unwrap(); }
+//! ```
+
+use std::fmt;
+
+use crate::ffi;
+use crate::{Connection, Error, Result, Transaction, TransactionBehavior};
+
+type StepFn<'a> = Box<dyn Fn(&Transaction<'_>) -> Result<()> + Send + Sync + 'a>;
+
+enum Action<'a> {
+    Sql(&'a str),
+    Fn(StepFn<'a>),
+}
+
+impl Action<'_> {
+    fn run(&self, tx: &Transaction<'_>) -> Result<()> {
+        match *self {
+            Action::Sql(sql) => tx.execute_batch(sql),
+            Action::Fn(ref f) => f(tx),
+        }
+    }
+}
+
+impl fmt::Debug for Action<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Action::Sql(sql) => f.debug_tuple("Sql").field(&sql).finish(),
+            Action::Fn(_) => f.write_str("Fn"),
+        }
+    }
+}
+
+/// One step of a [`Migrations`] list: an up migration and, optionally, the
+/// down migration reverting it.
+#[derive(Debug)]
+pub struct Step<'a> {
+    up: Action<'a>,
+    down: Option<Action<'a>>,
+}
+
+impl<'a> Step<'a> {
+    /// Step executing `sql` (which may contain several statements).
+    pub fn up(sql: &'a str) -> Step<'a> {
+        Step {
+            up: Action::Sql(sql),
+            down: None,
+        }
+    }
+
+    /// Step calling `f`.
+    pub fn up_with<F>(f: F) -> Step<'a>
+    where
+        F: Fn(&Transaction<'_>) -> Result<()> + Send + Sync + 'a,
+    {
+        Step {
+            up: Action::Fn(Box::new(f)),
+            down: None,
+        }
+    }
+
+    /// Make the step reversible by executing `sql`.
+    pub fn down(mut self, sql: &'a str) -> Step<'a> {
+        self.down = Some(Action::Sql(sql));
+        self
+    }
+
+    /// Make the step reversible by calling `f`.
+    pub fn down_with<F>(mut self, f: F) -> Step<'a>
+    where
+        F: Fn(&Transaction<'_>) -> Result<()> + Send + Sync + 'a,
+    {
+        self.down = Some(Action::Fn(Box::new(f)));
+        self
+    }
+}
+
+/// Ordered list of migration steps.
+#[derive(Debug)]
+pub struct Migrations<'a> {
+    steps: Vec<Step<'a>>,
+}
+
+impl<'a> Migrations<'a> {
+    /// Migrations made of `steps`; version `n` is reached once the first `n`
+    /// steps have been applied.
+    pub fn new(steps: Vec<Step<'a>>) -> Migrations<'a> {
+        Migrations { steps }
+    }
+
+    /// Version reached once all the steps have been applied.
+    pub fn latest_version(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Current version of the main database of `conn`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `user_version` cannot be read or is negative.
+    pub fn current_version(&self, conn: &Connection) -> Result<usize> {
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version < 0 {
+            return Err(Error::MigrationError(format!(
+                "invalid database version {}",
+                version
+            )));
+        }
+        Ok(version as usize)
+    }
+
+    /// Apply all the pending steps.
+    ///
+    /// # Failure
+    ///
+    /// See [`Migrations::to_version`].
+    pub fn to_latest(&self, conn: &mut Connection) -> Result<()> {
+        self.to_version(conn, self.latest_version())
+    }
+
+    /// Migrate the database up or down to `version`.
+    ///
+    /// Each step is committed separately: on failure, the database is left at
+    /// the version of the last successful step.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `version` (or the current version of the
+    /// database) is greater than the latest version, if a step needed to
+    /// migrate down is not reversible, if a step fails or if
+    /// `PRAGMA foreign_key_check` reports a violation after a step.
+    pub fn to_version(&self, conn: &mut Connection, version: usize) -> Result<()> {
+        self.check_version(version)?;
+        loop {
+            // the current version is read in the same transaction as the
+            // step, so that concurrent runners don't apply a step twice
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            if !self.next_step(&tx, version)? {
+                return Ok(());
+            }
+            tx.commit()?;
+        }
+    }
+
+    /// Migrate the database up or down to `version`, as
+    /// [`Migrations::to_version`] would, in a single transaction which is
+    /// then rolled back.
+    ///
+    /// # Failure
+    ///
+    /// Will return the error [`Migrations::to_version`] would.
+    pub fn dry_run(&self, conn: &mut Connection, version: usize) -> Result<()> {
+        self.check_version(version)?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        while self.next_step(&tx, version)? {}
+        tx.rollback()
+    }
+
+    fn check_version(&self, version: usize) -> Result<()> {
+        if version > self.latest_version() {
+            return Err(Error::MigrationError(format!(
+                "version {} is greater than the latest version {}",
+                version,
+                self.latest_version()
+            )));
+        }
+        Ok(())
+    }
+
+    // Apply the step moving the database one version closer to `target`,
+    // returns `false` if the database is already at `target`.
+    fn next_step(&self, tx: &Transaction<'_>, target: usize) -> Result<bool> {
+        let current = self.current_version(tx)?;
+        if current > self.latest_version() {
+            return Err(Error::MigrationError(format!(
+                "database version {} is greater than the latest version {}",
+                current,
+                self.latest_version()
+            )));
+        }
+        let new_version = if current < target {
+            self.steps[current].up.run(tx)?;
+            current + 1
+        } else if current > target {
+            match self.steps[current - 1].down {
+                Some(ref down) => down.run(tx)?,
+                None => {
+                    return Err(Error::MigrationError(format!(
+                        "migration {} is not reversible",
+                        current
+                    )))
+                }
+            }
+            current - 1
+        } else {
+            return Ok(false);
+        };
+        check_foreign_keys(tx, &format!("at version {}", new_version))?;
+        tx.pragma_update(None, "user_version", &(new_version as i64))?;
+        Ok(true)
+    }
+}
+
+/// Fail with `SQLITE_CONSTRAINT_FOREIGNKEY` if `PRAGMA foreign_key_check`
+/// reports a violation, `context` (e.g. "at version 2") completing the
+/// message.
+pub(crate) fn check_foreign_keys(conn: &Connection, context: &str) -> Result<()> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let mut rows = stmt.query([])?;
+    if let Some(row) = rows.next()? {
+        let table: String = row.get(0)?;
+        let rowid: Option<i64> = row.get(1)?;
+        let parent: String = row.get(2)?;
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_CONSTRAINT | (3 << 8)), // SQLITE_CONSTRAINT_FOREIGNKEY
+            Some(format!(
+                "FOREIGN KEY constraint failed {}: row {} of table {} references a missing row of table {}",
+                context,
+                rowid.map_or_else(|| "?".to_owned(), |r| r.to_string()),
+                table,
+                parent
+            )),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Migrations, Step};
+    use crate::{Connection, Error, Result};
+
+    fn migrations() -> Migrations<'static> {
+        Migrations::new(vec![
+            Step::up("CREATE TABLE parent(id INTEGER PRIMARY KEY);").down("DROP TABLE parent;"),
+            Step::up(
+                "CREATE TABLE child(id INTEGER PRIMARY KEY, parent_id INTEGER REFERENCES parent(id));",
+            )
+            .down("DROP TABLE child;"),
+            Step::up_with(|tx| {
+                tx.execute("INSERT INTO parent(id) VALUES (1)", [])?;
+                tx.execute("INSERT INTO child(parent_id) VALUES (1)", [])?;
+                Ok(())
+            }),
+        ])
+    }
+
+    fn tables(db: &Connection) -> Result<Vec<String>> {
+        let mut stmt =
+            db.prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        rows.collect()
+    }
+
+    #[test]
+    fn test_to_version() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        let migrations = migrations();
+        assert_eq!(0, migrations.current_version(&db)?);
+
+        migrations.to_version(&mut db, 2)?;
+        assert_eq!(2, migrations.current_version(&db)?);
+        assert_eq!(vec!["child", "parent"], tables(&db)?);
+
+        migrations.to_latest(&mut db)?;
+        assert_eq!(3, migrations.current_version(&db)?);
+        let n: i64 = db.query_row("SELECT count(*) FROM child", [], |r| r.get(0))?;
+        assert_eq!(1, n);
+
+        // the last step has no down migration
+        assert!(matches!(
+            migrations.to_version(&mut db, 0),
+            Err(Error::MigrationError(_))
+        ));
+        assert_eq!(3, migrations.current_version(&db)?);
+
+        db.execute_batch("DELETE FROM child; DELETE FROM parent; PRAGMA user_version = 2;")?;
+        migrations.to_version(&mut db, 0)?;
+        assert_eq!(0, migrations.current_version(&db)?);
+        assert!(tables(&db)?.is_empty());
+
+        assert!(matches!(
+            migrations.to_version(&mut db, 4),
+            Err(Error::MigrationError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        let migrations = migrations();
+        migrations.dry_run(&mut db, 3)?;
+        assert_eq!(0, migrations.current_version(&db)?);
+        assert!(tables(&db)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failing_step() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        let mut migrations = migrations();
+        migrations
+            .steps
+            .push(Step::up("INSERT INTO child(parent_id) VALUES (2);"));
+        migrations.steps.push(Step::up("CREATE TABLE foo(x);"));
+        match migrations.to_latest(&mut db) {
+            Err(Error::SqliteFailure(err, Some(msg))) => {
+                assert_eq!(crate::ErrorCode::ConstraintViolation, err.code);
+                assert!(msg.contains("version 4"), "{}", msg);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        // the failing step has been rolled back
+        assert_eq!(3, migrations.current_version(&db)?);
+        let n: i64 = db.query_row("SELECT count(*) FROM child", [], |r| r.get(0))?;
+        assert_eq!(1, n);
+
+        db.pragma_update(None, "user_version", &7)?;
+        assert!(matches!(
+            migrations.to_latest(&mut db),
+            Err(Error::MigrationError(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,498 @@
+//! Builder applying the usual setup of a new connection
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::options::{JournalMode, Synchronous};
+//! # use std::time::Duration;
+//! fn open_my_db() -> Result<Connection> {
+//!     Connection::options()
+//!         .journal_mode(JournalMode::Wal)
+//!         .synchronous(Synchronous::Normal)
+//!         .foreign_keys(true)
+//!         .busy_timeout(Duration::from_secs(5))
+//!         .init(|conn| conn.execute_batch("CREATE TABLE IF NOT EXISTS foo(x INTEGER)"))
+//!         .open("./my_db.db3")
+//! }
+//! ```
+
+use std::fmt;
+#[cfg(feature = "limits")]
+use std::os::raw::c_int;
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature = "limits")]
+use crate::ffi;
+#[cfg(feature = "limits")]
+use crate::limits::Limit;
+use crate::types::{FromSql, FromSqlError, FromSqlResult, Value, ValueRef};
+use crate::{Connection, Error, OpenFlags, Result};
+
+/// Journal mode, see [`PRAGMA journal_mode`](https://sqlite.org/pragma.html#pragma_journal_mode).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JournalMode {
+    /// Equivalent to `DELETE`
+    Delete,
+    /// Equivalent to `TRUNCATE`
+    Truncate,
+    /// Equivalent to `PERSIST`
+    Persist,
+    /// Equivalent to `MEMORY`
+    Memory,
+    /// Equivalent to `WAL`
+    Wal,
+    /// Equivalent to `OFF`
+    Off,
+}
+
+impl JournalMode {
+    /// Keyword of the journal mode, as used by `PRAGMA journal_mode`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JournalMode::Delete => "DELETE",
+            JournalMode::Truncate => "TRUNCATE",
+            JournalMode::Persist => "PERSIST",
+            JournalMode::Memory => "MEMORY",
+            JournalMode::Wal => "WAL",
+            JournalMode::Off => "OFF",
+        }
+    }
+}
+
+impl FromSql for JournalMode {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        [
+            JournalMode::Delete,
+            JournalMode::Truncate,
+            JournalMode::Persist,
+            JournalMode::Memory,
+            JournalMode::Wal,
+            JournalMode::Off,
+        ]
+        .iter()
+        .copied()
+        .find(|mode| mode.as_str().eq_ignore_ascii_case(s))
+        .ok_or_else(|| FromSqlError::Other(format!("invalid journal mode: {}", s).into()))
+    }
+}
+
+/// Synchronous flag, see [`PRAGMA synchronous`](https://sqlite.org/pragma.html#pragma_synchronous).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Synchronous {
+    /// Equivalent to `OFF`
+    Off,
+    /// Equivalent to `NORMAL`
+    Normal,
+    /// Equivalent to `FULL`
+    Full,
+    /// Equivalent to `EXTRA`
+    Extra,
+}
+
+impl Synchronous {
+    /// Keyword of the synchronous flag, as used by `PRAGMA synchronous`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Synchronous::Off => "OFF",
+            Synchronous::Normal => "NORMAL",
+            Synchronous::Full => "FULL",
+            Synchronous::Extra => "EXTRA",
+        }
+    }
+}
+
+impl FromSql for Synchronous {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(Synchronous::Off),
+            1 => Ok(Synchronous::Normal),
+            2 => Ok(Synchronous::Full),
+            3 => Ok(Synchronous::Extra),
+            n => Err(FromSqlError::OutOfRange(n)),
+        }
+    }
+}
+
+/// Value of the `cache` URI parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheMode {
+    /// The connection uses a private cache (`cache=private`)
+    Private,
+    /// The connection shares its cache with the other connections to the same
+    /// database in the process (`cache=shared`)
+    Shared,
+}
+
+type InitFn = Box<dyn FnOnce(&mut Connection) -> Result<()>>;
+
+/// Builder of a [`Connection`], created by [`Connection::options`].
+///
+/// The settings are applied by [`OpenOptions::open`] in the following order:
+/// encryption key, limits, busy timeout, pragmas (in the order they have been
+/// set) and finally the `init` closure.
+pub struct OpenOptions {
+    flags: OpenFlags,
+    vfs: Option<String>,
+    uri_params: Vec<(&'static str, &'static str)>,
+    key: Option<String>,
+    #[cfg(feature = "limits")]
+    limits: Vec<(c_int, i32)>,
+    busy_timeout: Option<Duration>,
+    pragmas: Vec<(String, Value)>,
+    init: Option<InitFn>,
+}
+
+impl Default for OpenOptions {
+    fn default() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+impl OpenOptions {
+    /// Options opening a database with the default flags and without any
+    /// further setup, like [`Connection::open`].
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            flags: OpenFlags::default(),
+            vfs: None,
+            uri_params: Vec::new(),
+            key: None,
+            #[cfg(feature = "limits")]
+            limits: Vec::new(),
+            busy_timeout: None,
+            pragmas: Vec::new(),
+            init: None,
+        }
+    }
+
+    /// Flags passed to `sqlite3_open_v2`.
+    ///
+    /// `SQLITE_OPEN_URI` is always added when an URI parameter is set.
+    pub fn flags(mut self, flags: OpenFlags) -> OpenOptions {
+        self.flags = flags;
+        self
+    }
+
+    /// Name of the VFS used to open the database.
+    pub fn vfs(mut self, vfs: &str) -> OpenOptions {
+        self.vfs = Some(vfs.to_owned());
+        self
+    }
+
+    /// Set the `immutable` URI parameter: the database file is assumed not
+    /// to change, even by other processes, so no locking nor change detection
+    /// is done.
+    pub fn immutable(self, immutable: bool) -> OpenOptions {
+        self.uri_param("immutable", if immutable { "1" } else { "0" })
+    }
+
+    /// Set the `nolock` URI parameter: no file locking is done.
+    pub fn nolock(self, nolock: bool) -> OpenOptions {
+        self.uri_param("nolock", if nolock { "1" } else { "0" })
+    }
+
+    /// Set the `cache` URI parameter.
+    pub fn cache(self, cache: CacheMode) -> OpenOptions {
+        self.uri_param(
+            "cache",
+            match cache {
+                CacheMode::Private => "private",
+                CacheMode::Shared => "shared",
+            },
+        )
+    }
+
+    /// Set the `mode=memory` URI parameter: the database is a pure in-memory
+    /// database, named after the path given to [`OpenOptions::open`].
+    ///
+    /// Combined with [`CacheMode::Shared`], this allows several connections to
+    /// use the same in-memory database.
+    pub fn memory(self) -> OpenOptions {
+        self.uri_param("mode", "memory")
+    }
+
+    fn uri_param(mut self, name: &'static str, value: &'static str) -> OpenOptions {
+        self.uri_params.retain(|(n, _)| *n != name);
+        self.uri_params.push((name, value));
+        self
+    }
+
+    /// Encryption key, set with `PRAGMA key` before anything else is done on
+    /// the connection.
+    ///
+    /// Only meaningful if the linked library supports encryption (e.g.
+    /// SQLCipher); the pragma is silently ignored otherwise.
+    pub fn key(mut self, key: &str) -> OpenOptions {
+        self.key = Some(key.to_owned());
+        self
+    }
+
+    /// Set a run-time limit, see [`Connection::set_limit`].
+    #[cfg(feature = "limits")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "limits")))]
+    pub fn limit(mut self, limit: Limit, value: i32) -> OpenOptions {
+        let limit = limit as c_int;
+        self.limits.retain(|(l, _)| *l != limit);
+        self.limits.push((limit, value));
+        self
+    }
+
+    /// Set the busy timeout, see [`Connection::busy_timeout`].
+    pub fn busy_timeout(mut self, timeout: Duration) -> OpenOptions {
+        self.busy_timeout = Some(timeout);
+        self
+    }
+
+    /// Set `PRAGMA journal_mode`.
+    pub fn journal_mode(self, mode: JournalMode) -> OpenOptions {
+        self.pragma("journal_mode", mode.as_str().to_owned())
+    }
+
+    /// Set `PRAGMA synchronous`.
+    pub fn synchronous(self, synchronous: Synchronous) -> OpenOptions {
+        self.pragma("synchronous", synchronous.as_str().to_owned())
+    }
+
+    /// Set `PRAGMA foreign_keys`.
+    pub fn foreign_keys(self, enabled: bool) -> OpenOptions {
+        self.pragma("foreign_keys", enabled)
+    }
+
+    /// Set `PRAGMA cache_size`: a number of pages if positive, a number of
+    /// KiB if negative.
+    pub fn cache_size(self, size: i64) -> OpenOptions {
+        self.pragma("cache_size", size)
+    }
+
+    /// Set `PRAGMA mmap_size`, in bytes.
+    pub fn mmap_size(self, size: i64) -> OpenOptions {
+        self.pragma("mmap_size", size)
+    }
+
+    /// Set any pragma on the main database.
+    ///
+    /// Setting the same pragma twice only keeps the last value.
+    pub fn pragma<V: Into<Value>>(mut self, name: &str, value: V) -> OpenOptions {
+        self.pragmas.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.pragmas.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Closure called once everything else has been applied, e.g. to
+    /// register functions or create the schema.
+    pub fn init<F>(mut self, init: F) -> OpenOptions
+    where
+        F: FnOnce(&mut Connection) -> Result<()> + 'static,
+    {
+        self.init = Some(Box::new(init));
+        self
+    }
+
+    /// Open the database at `path` and apply the options.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the database cannot be opened or if applying one
+    /// of the options fails, in which case the connection is closed and the
+    /// first error is returned.
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<Connection> {
+        let path = path.as_ref();
+        let mut flags = self.flags;
+        let mut conn = if self.uri_params.is_empty() {
+            self.open_path(path, flags)?
+        } else {
+            flags |= OpenFlags::SQLITE_OPEN_URI;
+            let mut conn = self.open_path(&self.uri(path)?, flags)?;
+            conn.path = Some(path.to_path_buf());
+            conn
+        };
+        if let Err(err) = self.apply(&mut conn) {
+            let _ = conn.close();
+            return Err(err);
+        }
+        Ok(conn)
+    }
+
+    /// Open a new in-memory database and apply the options.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the database cannot be opened or if applying one
+    /// of the options fails.
+    pub fn open_in_memory(self) -> Result<Connection> {
+        self.open(":memory:")
+    }
+
+    fn open_path(&self, path: &Path, flags: OpenFlags) -> Result<Connection> {
+        match self.vfs {
+            Some(ref vfs) => Connection::open_with_flags_and_vfs(path, flags, vfs),
+            None => Connection::open_with_flags(path, flags),
+        }
+    }
+
+    // `file:` URI with `path` percent-encoded as needed and the URI parameters
+    fn uri(&self, path: &Path) -> Result<std::path::PathBuf> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| Error::InvalidPath(path.to_owned()))?;
+        let mut uri = String::from(if path.starts_with('/') {
+            "file://"
+        } else {
+            "file:"
+        });
+        for c in path.chars() {
+            match c {
+                '?' => uri.push_str("%3f"),
+                '#' => uri.push_str("%23"),
+                '%' => uri.push_str("%25"),
+                c => uri.push(c),
+            }
+        }
+        for (i, (name, value)) in self.uri_params.iter().enumerate() {
+            uri.push(if i == 0 { '?' } else { '&' });
+            uri.push_str(name);
+            uri.push('=');
+            uri.push_str(value);
+        }
+        Ok(uri.into())
+    }
+
+    fn apply(self, conn: &mut Connection) -> Result<()> {
+        if let Some(ref key) = self.key {
+            conn.pragma(None, "key", &key, |_| Ok(()))?;
+        }
+        #[cfg(feature = "limits")]
+        for (limit, value) in self.limits {
+            let c = conn.db.borrow_mut();
+            unsafe { ffi::sqlite3_limit(c.db(), limit, value) };
+        }
+        if let Some(timeout) = self.busy_timeout {
+            conn.busy_timeout(timeout)?;
+        }
+        for (name, value) in &self.pragmas {
+            // some pragmas (e.g. journal_mode) return the new value
+            conn.pragma(None, name, value, |_| Ok(()))?;
+        }
+        if let Some(init) = self.init {
+            init(conn)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for OpenOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("OpenOptions");
+        s.field("flags", &self.flags)
+            .field("vfs", &self.vfs)
+            .field("uri_params", &self.uri_params)
+            // never print the encryption key
+            .field("key", &self.key.as_ref().map(|_| "***"));
+        #[cfg(feature = "limits")]
+        s.field("limits", &self.limits);
+        s.field("busy_timeout", &self.busy_timeout)
+            .field("pragmas", &self.pragmas)
+            .field("init", &self.init.is_some())
+            .finish()
+    }
+}
+
+impl Connection {
+    /// Builder opening a connection and applying the usual setup (pragmas,
+    /// URI parameters, limits, ...) in one go.
+    ///
+    /// See [`OpenOptions`].
+    pub fn options() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CacheMode, JournalMode, OpenOptions, Synchronous};
+    use crate::{Connection, Error, Result};
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pragmas() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db3");
+        let db = Connection::options()
+            .journal_mode(JournalMode::Wal)
+            .synchronous(Synchronous::Normal)
+            .foreign_keys(true)
+            .cache_size(-4000)
+            .cache_size(-8000)
+            .busy_timeout(Duration::from_millis(100))
+            .init(|conn| conn.execute_batch("CREATE TABLE foo(x INTEGER)"))
+            .open(&path)?;
+        let mode: String = db.pragma_query_value(None, "journal_mode", |r| r.get(0))?;
+        assert_eq!("wal", mode);
+        let synchronous: i64 = db.pragma_query_value(None, "synchronous", |r| r.get(0))?;
+        assert_eq!(1, synchronous);
+        let fk: bool = db.pragma_query_value(None, "foreign_keys", |r| r.get(0))?;
+        assert!(fk);
+        let cache_size: i64 = db.pragma_query_value(None, "cache_size", |r| r.get(0))?;
+        assert_eq!(-8000, cache_size);
+        db.execute("INSERT INTO foo VALUES (1)", [])?;
+        assert_eq!(Some(path.as_path()), db.path());
+        Ok(())
+    }
+
+    #[test]
+    fn test_uri_params() -> Result<()> {
+        let db1 = Connection::options()
+            .memory()
+            .cache(CacheMode::Shared)
+            .open("options?test#1")?;
+        db1.execute_batch("CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES (42);")?;
+        let db2 = OpenOptions::new()
+            .memory()
+            .cache(CacheMode::Shared)
+            .open("options?test#1")?;
+        let x: i64 = db2.query_row("SELECT x FROM foo", [], |r| r.get(0))?;
+        assert_eq!(42, x);
+        assert_eq!(Some(Path::new("options?test#1")), db2.path());
+        Ok(())
+    }
+
+    #[test]
+    fn test_immutable() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test%20.db3");
+        Connection::open(&path)?.execute_batch("CREATE TABLE foo(x INTEGER)")?;
+        let db = Connection::options().immutable(true).open(&path)?;
+        db.query_row("SELECT count(*) FROM foo", [], |_| Ok(()))?;
+        assert!(db.execute("INSERT INTO foo VALUES (1)", []).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "limits")]
+    #[test]
+    fn test_limit() -> Result<()> {
+        use crate::limits::Limit;
+        let db = Connection::options()
+            .limit(Limit::SQLITE_LIMIT_SQL_LENGTH, 1024)
+            .open_in_memory()?;
+        assert_eq!(1024, db.limit(Limit::SQLITE_LIMIT_SQL_LENGTH));
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_error() {
+        let result = Connection::options()
+            .pragma("foreign_keys", vec![0u8])
+            .init(|_| panic!("init called after a failure"))
+            .open_in_memory();
+        assert!(result.is_err());
+
+        let result = Connection::options()
+            .init(|_| Err(Error::InvalidQuery))
+            .open_in_memory();
+        assert_eq!(Some(Error::InvalidQuery), result.err());
+    }
+}
@@ -0,0 +1,297 @@
+use std::fmt;
+use std::mem::{self, ManuallyDrop};
+use std::ptr::{self, NonNull};
+use std::rc::Rc;
+
+use super::{Connection, Params, Result, Row, Rows, Statement, ToSql};
+
+/// A prepared statement sharing ownership of its connection, so that it can
+/// be returned from functions or stored next to the connection.
+///
+/// ```rust
+/// use rusqlite::{Connection, OwnedStatement, Result};
+/// use std::rc::Rc;
+///
+/// fn names(conn: &Rc<Connection>) -> Result<impl Iterator<Item = Result<String>>> {
+///     let stmt = OwnedStatement::new(conn.clone(), "SELECT name FROM person")?;
+///     stmt.query_map([], |row| row.get(0))
+/// }
+///
+/// fn main() -> Result<()> {
+///     let conn = Rc::new(Connection::open_in_memory()?);
+///     conn.execute_batch("CREATE TABLE person (name TEXT); INSERT INTO person VALUES ('Ada');")?;
+///     let names: Vec<String> = names(&conn)?.collect::<Result<_>>()?;
+///     assert_eq!(vec!["Ada"], names);
+///     Ok(())
+/// }
+/// ```
+pub struct OwnedStatement {
+    // borrows `*conn`: declared first so that it is dropped first
+    stmt: Statement<'static>,
+    conn: Rc<Connection>,
+}
+
+impl OwnedStatement {
+    /// Prepare `sql` on `conn`.
+    ///
+    /// # Failure
+    ///
+    /// See [`Connection::prepare`].
+    pub fn new(conn: Rc<Connection>, sql: &str) -> Result<OwnedStatement> {
+        let stmt = conn.prepare(sql)?;
+        // Safety: the connection lives on the heap until `conn` is dropped,
+        // which happens after `stmt` is dropped. `stmt` is never exposed with
+        // the `'static` lifetime.
+        let stmt = unsafe { mem::transmute::<Statement<'_>, Statement<'static>>(stmt) };
+        Ok(OwnedStatement { stmt, conn })
+    }
+
+    /// The connection of the statement.
+    #[inline]
+    pub fn connection(&self) -> &Rc<Connection> {
+        &self.conn
+    }
+
+    /// The statement, to call the methods which don't need a mutable
+    /// reference (e.g. [`Statement::column_names`]).
+    #[inline]
+    pub fn as_statement(&self) -> &Statement<'_> {
+        &self.stmt
+    }
+
+    /// See [`Statement::execute`].
+    #[inline]
+    pub fn execute<P: Params>(&mut self, params: P) -> Result<usize> {
+        self.stmt.execute(params)
+    }
+
+    /// See [`Statement::query_row`].
+    #[inline]
+    pub fn query_row<T, P, F>(&mut self, params: P, f: F) -> Result<T>
+    where
+        P: Params,
+        F: FnOnce(&Row<'_>) -> Result<T>,
+    {
+        self.stmt.query_row(params, f)
+    }
+
+    /// See [`Statement::raw_bind_parameter`].
+    #[inline]
+    pub fn raw_bind_parameter<T: ToSql>(
+        &mut self,
+        one_based_col_index: usize,
+        param: T,
+    ) -> Result<()> {
+        self.stmt.raw_bind_parameter(one_based_col_index, param)
+    }
+
+    /// Execute the statement, returning the resulting rows, which own the
+    /// statement. See [`Statement::query`].
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if binding parameters fails.
+    pub fn query<P: Params>(mut self, params: P) -> Result<OwnedRows> {
+        params.__bind_in(&mut self.stmt)?;
+        Ok(OwnedRows::new(self))
+    }
+
+    /// Execute the statement with the parameters bound by
+    /// [`OwnedStatement::raw_bind_parameter`]. See [`Statement::raw_query`].
+    pub fn raw_query(self) -> OwnedRows {
+        OwnedRows::new(self)
+    }
+
+    /// Execute the statement, returning an iterator over the result of `f`
+    /// applied to each row. See [`Statement::query_map`].
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if binding parameters fails.
+    pub fn query_map<T, P, F>(self, params: P, f: F) -> Result<OwnedMappedRows<F>>
+    where
+        P: Params,
+        F: FnMut(&Row<'_>) -> Result<T>,
+    {
+        self.query(params).map(|rows| rows.mapped(f))
+    }
+}
+
+impl fmt::Debug for OwnedStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedStatement").field(&self.stmt).finish()
+    }
+}
+
+/// The rows of an [`OwnedStatement`], owning the statement.
+pub struct OwnedRows {
+    // borrows `*stmt`, which is dropped after it
+    rows: ManuallyDrop<Rows<'static>>,
+    // allocated by `Box`
+    stmt: NonNull<Statement<'static>>,
+    conn: Rc<Connection>,
+}
+
+impl OwnedRows {
+    fn new(stmt: OwnedStatement) -> OwnedRows {
+        let OwnedStatement { stmt, conn } = stmt;
+        let stmt = NonNull::from(Box::leak(Box::new(stmt)));
+        // Safety: the statement is not moved nor dropped until `rows` is
+        // dropped, and `rows` is never exposed with the `'static` lifetime.
+        let rows = Rows::new(unsafe { &*stmt.as_ptr() });
+        OwnedRows {
+            rows: ManuallyDrop::new(rows),
+            stmt,
+            conn,
+        }
+    }
+
+    /// See [`Rows::next`].
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if stepping the statement fails.
+    #[allow(clippy::should_implement_trait)] // cannot implement Iterator
+    #[inline]
+    pub fn next(&mut self) -> Result<Option<&Row<'_>>> {
+        self.rows.next()
+    }
+
+    /// Convert the rows into an iterator over the result of `f` applied to
+    /// each row.
+    #[inline]
+    pub fn mapped<T, F>(self, f: F) -> OwnedMappedRows<F>
+    where
+        F: FnMut(&Row<'_>) -> Result<T>,
+    {
+        OwnedMappedRows { rows: self, map: f }
+    }
+
+    /// Reset the statement and give it back.
+    pub fn into_statement(self) -> OwnedStatement {
+        let mut this = ManuallyDrop::new(self);
+        // Safety: each field is dropped or moved out once, `rows` first.
+        unsafe {
+            ManuallyDrop::drop(&mut this.rows);
+            let stmt = *Box::from_raw(this.stmt.as_ptr());
+            let conn = ptr::read(&this.conn);
+            OwnedStatement { stmt, conn }
+        }
+    }
+}
+
+impl Drop for OwnedRows {
+    fn drop(&mut self) {
+        // Safety: `rows` is not used anymore, the statement is dropped after
+        // it and before the connection.
+        unsafe {
+            ManuallyDrop::drop(&mut self.rows);
+            drop(Box::from_raw(self.stmt.as_ptr()));
+        }
+    }
+}
+
+impl fmt::Debug for OwnedRows {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Safety: the statement is alive as long as `self`
+        let stmt: &Statement<'_> = unsafe { self.stmt.as_ref() };
+        f.debug_tuple("OwnedRows").field(stmt).finish()
+    }
+}
+
+/// An iterator over the mapped rows of an [`OwnedStatement`], created by
+/// [`OwnedStatement::query_map`].
+pub struct OwnedMappedRows<F> {
+    rows: OwnedRows,
+    map: F,
+}
+
+impl<F> OwnedMappedRows<F> {
+    /// Reset the statement and give it back.
+    #[inline]
+    pub fn into_statement(self) -> OwnedStatement {
+        self.rows.into_statement()
+    }
+}
+
+impl<T, F> Iterator for OwnedMappedRows<F>
+where
+    F: FnMut(&Row<'_>) -> Result<T>,
+{
+    type Item = Result<T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Result<T>> {
+        let map = &mut self.map;
+        self.rows
+            .next()
+            .transpose()
+            .map(|row_result| row_result.and_then(map))
+    }
+}
+
+impl<F> fmt::Debug for OwnedMappedRows<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedMappedRows").field(&self.rows).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OwnedMappedRows, OwnedStatement};
+    use crate::{Connection, Result, Row};
+    use std::rc::Rc;
+
+    type RowFn = fn(&Row<'_>) -> Result<i64>;
+
+    struct Cursor {
+        rows: OwnedMappedRows<RowFn>,
+    }
+
+    fn first_column(row: &Row<'_>) -> Result<i64> {
+        row.get(0)
+    }
+
+    fn db() -> Result<Rc<Connection>> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES (1), (2), (3);")?;
+        Ok(Rc::new(db))
+    }
+
+    #[test]
+    fn test_owned_rows() -> Result<()> {
+        let db = db()?;
+        let stmt = OwnedStatement::new(db.clone(), "SELECT x FROM foo WHERE x > ? ORDER BY x")?;
+        let mut rows = stmt.query([1])?;
+        assert_eq!(2, rows.next()?.unwrap().get::<_, i64>(0)?);
+
+        // the statement is reset when the rows are dropped
+        let mut stmt = rows.into_statement();
+        assert_eq!(vec!["x"], stmt.as_statement().column_names());
+        let n: i64 = stmt.query_row([2], |r| r.get(0))?;
+        assert_eq!(3, n);
+
+        let mut cursor = Cursor {
+            rows: stmt.query_map([0], first_column as RowFn)?,
+        };
+        // the connection is released last
+        drop(db);
+        assert_eq!(Some(1), cursor.rows.next().transpose()?);
+        let rest: Vec<i64> = cursor.rows.collect::<Result<_>>()?;
+        assert_eq!(vec![2, 3], rest);
+        Ok(())
+    }
+
+    #[test]
+    fn test_owned_execute() -> Result<()> {
+        let db = db()?;
+        let mut stmt = OwnedStatement::new(db.clone(), "INSERT INTO foo VALUES (?)")?;
+        stmt.execute([4])?;
+        stmt.raw_bind_parameter(1, 5)?;
+        assert!(Rc::ptr_eq(&db, stmt.connection()));
+        drop(stmt);
+        let n: i64 = db.query_row("SELECT count(*) FROM foo", [], |r| r.get(0))?;
+        assert_eq!(4, n);
+        Ok(())
+    }
+}
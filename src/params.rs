@@ -1,4 +1,10 @@
-use crate::{Result, Statement, ToSql};
+//! Parameters of SQL statements, see [`Params`].
+use crate::types::ToSqlOutput;
+use crate::{Error, Result, Statement, ToSql};
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod serde;
 
 mod sealed {
     /// This trait exists just to ensure that the only impls of `trait Params`
@@ -356,3 +362,61 @@ where
         stmt.bind_parameters(self.0)
     }
 }
+
+/// Types which can be bound as a set of named parameters.
+///
+/// A reference to such a type implements [`Params`]: every parameter of the
+/// statement must be provided, while values which do not match any
+/// parameter are ignored. The implementation is
+/// usually generated with `#[derive(ToParams)]` (which requires the `derive`
+/// feature), binding each field to the parameter with the same name prefixed
+/// with `:`.
+///
+/// ```rust,no_run
+/// # use rusqlite::{Connection, Result};
+/// use rusqlite::params::ToParams;
+/// use rusqlite::types::{ToSql, ToSqlOutput};
+///
+/// struct Person {
+///     name: String,
+///     age: u32,
+/// }
+///
+/// impl ToParams for Person {
+///     fn to_params(&self) -> Result<Vec<(&'static str, ToSqlOutput<'_>)>> {
+///         Ok(vec![(":name", self.name.to_sql()?), (":age", self.age.to_sql()?)])
+///     }
+/// }
+///
+/// fn insert(conn: &Connection, person: &Person) -> Result<usize> {
+///     conn.execute("INSERT INTO person (name, age) VALUES (:name, :age)", person)
+/// }
+/// ```
+pub trait ToParams {
+    /// Returns the name, including its prefix, and the value of each
+    /// parameter.
+    fn to_params(&self) -> Result<Vec<(&'static str, ToSqlOutput<'_>)>>;
+}
+
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use rusqlite_macros::ToParams;
+
+impl<T: ToParams + ?Sized> Sealed for &T {}
+impl<T: ToParams + ?Sized> Params for &T {
+    fn __bind_in(self, stmt: &mut Statement<'_>) -> Result<()> {
+        let params = self.to_params()?;
+        for index in 1..=stmt.parameter_count() {
+            let name = stmt.parameter_name(index);
+            match name.and_then(|name| params.iter().find(|(param, _)| *param == name)) {
+                Some((_, value)) => stmt.raw_bind_parameter(index, value)?,
+                None => {
+                    return Err(Error::InvalidParameterName(
+                        name.map_or_else(|| format!("?{}", index), str::to_owned),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,610 @@
+//! Bind the fields of a `Serialize` value as named parameters.
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! use rusqlite::params::serde::named_params_from;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Person {
+//!     id: i64,
+//!     name: String,
+//!     email: Option<String>,
+//! }
+//!
+//! fn insert(conn: &Connection, person: &Person) -> Result<usize> {
+//!     conn.execute(
+//!         "INSERT INTO person (id, name, email) VALUES (:id, :name, :email)",
+//!         named_params_from(person),
+//!     )
+//! }
+//! ```
+use std::error;
+use std::fmt;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use super::{Params, Sealed};
+use crate::types::Value;
+use crate::{Error, Result, Statement};
+
+/// What to do with a field without parameter, or with a parameter without
+/// field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unmatched {
+    /// Skip the field, or leave the parameter unchanged
+    Ignore,
+    /// Fail with `Error::InvalidParameterName`, whose payload is the
+    /// parameter name as [`Statement::parameter_name`] reports it (with a
+    /// `:` prefix for a field without parameter, or `?N` for an anonymous
+    /// parameter)
+    Error,
+}
+
+/// [`Params`] binding the fields of a struct (or the entries of a map) to the
+/// parameters with the same name, created by [`named_params_from`].
+///
+/// Parameter names are matched without their prefix (`:`, `@` or `$`).
+///
+/// Values are converted like [`Row::deserialize`](crate::Row::deserialize)
+/// expects them: `None` and `()` are bound as `NULL`, newtype structs as the
+/// value they wrap, field-less enum variants as their name and, with the
+/// `serde_json` feature, other structs, maps, sequences and enum variants as
+/// JSON text.
+#[derive(Debug)]
+pub struct NamedParams<'a, T: ?Sized> {
+    value: &'a T,
+    unused_fields: Unmatched,
+    missing_params: Unmatched,
+}
+
+/// Bind the fields of `value` as named parameters.
+///
+/// By default, fields without parameter are ignored and parameters without
+/// field are an error.
+#[inline]
+pub fn named_params_from<T: Serialize + ?Sized>(value: &T) -> NamedParams<'_, T> {
+    NamedParams {
+        value,
+        unused_fields: Unmatched::Ignore,
+        missing_params: Unmatched::Error,
+    }
+}
+
+impl<T: ?Sized> NamedParams<'_, T> {
+    /// What to do with the fields which don't match any parameter.
+    #[inline]
+    pub fn unused_fields(mut self, unmatched: Unmatched) -> Self {
+        self.unused_fields = unmatched;
+        self
+    }
+
+    /// What to do with the parameters which don't match any field.
+    #[inline]
+    pub fn missing_params(mut self, unmatched: Unmatched) -> Self {
+        self.missing_params = unmatched;
+        self
+    }
+}
+
+impl<T: Serialize + ?Sized> Sealed for NamedParams<'_, T> {}
+
+impl<T: Serialize + ?Sized> Params for NamedParams<'_, T> {
+    fn __bind_in(self, stmt: &mut Statement<'_>) -> Result<()> {
+        let fields = self
+            .value
+            .serialize(FieldsSerializer)
+            .map_err(|err| Error::ToSqlConversionFailure(Box::new(err)))?;
+        let mut used = vec![false; fields.len()];
+        for index in 1..=stmt.parameter_count() {
+            let name = stmt.parameter_name(index);
+            let field = name.and_then(|name| {
+                let name = name.trim_start_matches(&[':', '@', '$'][..]);
+                fields.iter().position(|(field, _)| field == name)
+            });
+            match field {
+                Some(i) => {
+                    used[i] = true;
+                    stmt.raw_bind_parameter(index, &fields[i].1)?;
+                }
+                None if self.missing_params == Unmatched::Error => {
+                    return Err(Error::InvalidParameterName(
+                        name.map_or_else(|| format!("?{}", index), str::to_owned),
+                    ));
+                }
+                None => {}
+            }
+        }
+        if self.unused_fields == Unmatched::Error {
+            if let Some(i) = used.iter().position(|used| !used) {
+                // named like the parameter it lacks, with the `:` prefix
+                let field = &fields[i].0;
+                return Err(Error::InvalidParameterName(format!(":{}", field)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error while serializing parameters, reported through
+/// `Error::ToSqlConversionFailure`.
+#[derive(Debug)]
+enum SerError {
+    Message(String),
+    // not a plain value: serialized as JSON if possible
+    Compound,
+}
+
+impl fmt::Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SerError::Message(ref msg) => msg.fmt(f),
+            SerError::Compound => write!(
+                f,
+                "cannot bind a compound value without the `serde_json` feature"
+            ),
+        }
+    }
+}
+
+impl error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<T: fmt::Display>(msg: T) -> SerError {
+        SerError::Message(msg.to_string())
+    }
+}
+
+fn field_value<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<Value, SerError> {
+    match value.serialize(ValueSerializer) {
+        #[cfg(feature = "serde_json")]
+        Err(SerError::Compound) => serde_json::to_string(value)
+            .map(Value::Text)
+            .map_err(|err| SerError::Message(format!("field `{}`: {}", name, err))),
+        Err(err) => Err(SerError::Message(format!("field `{}`: {}", name, err))),
+        ok => ok,
+    }
+}
+
+/// Serializer of a struct or map into a list of fields.
+struct FieldsSerializer;
+
+struct Fields {
+    fields: Vec<(String, Value)>,
+    key: Option<String>,
+}
+
+impl Fields {
+    fn new(len: Option<usize>) -> Fields {
+        Fields {
+            fields: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        }
+    }
+}
+
+macro_rules! not_fields {
+    ($($method:ident($($arg:ty),*))*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Vec<(String, Value)>, SerError> {
+                Err(SerError::Message("expected a struct or a map".to_owned()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for FieldsSerializer {
+    type Ok = Vec<(String, Value)>;
+    type Error = SerError;
+    type SerializeSeq = Impossible<Self::Ok, SerError>;
+    type SerializeTuple = Impossible<Self::Ok, SerError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, SerError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, SerError>;
+    type SerializeMap = Fields;
+    type SerializeStruct = Fields;
+    type SerializeStructVariant = Impossible<Self::Ok, SerError>;
+
+    not_fields! {
+        serialize_bool(bool) serialize_i8(i8) serialize_i16(i16) serialize_i32(i32)
+        serialize_i64(i64) serialize_u8(u8) serialize_u16(u16) serialize_u32(u32)
+        serialize_u64(u64) serialize_f32(f32) serialize_f64(f64) serialize_char(char)
+        serialize_str(&str) serialize_bytes(&[u8]) serialize_none() serialize_unit()
+        serialize_unit_struct(&'static str)
+        serialize_unit_variant(&'static str, u32, &'static str)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, SerError> {
+        Err(SerError::Message("expected a struct or a map".to_owned()))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        Err(SerError::Message("expected a struct or a map".to_owned()))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerError> {
+        Err(SerError::Message("expected a struct or a map".to_owned()))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        Err(SerError::Message("expected a struct or a map".to_owned()))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(SerError::Message("expected a struct or a map".to_owned()))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        Ok(Fields::new(len))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        Ok(Fields::new(Some(len)))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(SerError::Message("expected a struct or a map".to_owned()))
+    }
+}
+
+impl ser::SerializeMap for Fields {
+    type Ok = Vec<(String, Value)>;
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        match key.serialize(ValueSerializer)? {
+            Value::Text(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(SerError::Message("map keys must be strings".to_owned())),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        let value = field_value(&key, value)?;
+        self.fields.push((key, value));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        Ok(self.fields)
+    }
+}
+
+impl ser::SerializeStruct for Fields {
+    type Ok = Vec<(String, Value)>;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        let value = field_value(key, value)?;
+        self.fields.push((key.to_owned(), value));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        Ok(self.fields)
+    }
+}
+
+/// Serializer of a field into a `Value`.
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SerError;
+    type SerializeSeq = Impossible<Value, SerError>;
+    type SerializeTuple = Impossible<Value, SerError>;
+    type SerializeTupleStruct = Impossible<Value, SerError>;
+    type SerializeTupleVariant = Impossible<Value, SerError>;
+    type SerializeMap = Impossible<Value, SerError>;
+    type SerializeStruct = Impossible<Value, SerError>;
+    type SerializeStructVariant = Impossible<Value, SerError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, SerError> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, SerError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, SerError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, SerError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, SerError> {
+        Ok(Value::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, SerError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, SerError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, SerError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, SerError> {
+        if v > i64::MAX as u64 {
+            return Err(SerError::Message(format!("integer {} out of range", v)));
+        }
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, SerError> {
+        Ok(Value::Real(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, SerError> {
+        Ok(Value::Real(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, SerError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, SerError> {
+        Ok(Value::Text(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, SerError> {
+        Ok(Value::Blob(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, SerError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, SerError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, SerError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, SerError> {
+        Ok(Value::Text(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, SerError> {
+        Err(SerError::Compound)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        Err(SerError::Compound)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerError> {
+        Err(SerError::Compound)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        Err(SerError::Compound)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(SerError::Compound)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        Err(SerError::Compound)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        Err(SerError::Compound)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(SerError::Compound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::{named_params_from, Unmatched};
+    use crate::{Connection, Error, Result};
+
+    #[derive(Serialize)]
+    struct Id(i64);
+
+    #[derive(Serialize)]
+    enum Kind {
+        Cat,
+    }
+
+    #[derive(Serialize)]
+    struct Pet {
+        id: Id,
+        name: &'static str,
+        kind: Kind,
+        age: Option<i64>,
+        vaccinated: bool,
+    }
+
+    const FELIX: Pet = Pet {
+        id: Id(1),
+        name: "Felix",
+        kind: Kind::Cat,
+        age: None,
+        vaccinated: true,
+    };
+
+    #[test]
+    fn test_named_params_from() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE pet (id INTEGER, name TEXT, kind TEXT, age INTEGER, vaccinated INTEGER)",
+        )?;
+        db.execute(
+            "INSERT INTO pet VALUES (:id, @name, $kind, :age, :vaccinated)",
+            named_params_from(&FELIX),
+        )?;
+        let row: (i64, String, String, Option<i64>, bool) =
+            db.query_row("SELECT * FROM pet", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))
+            })?;
+        assert_eq!((1, "Felix".to_owned(), "Cat".to_owned(), None, true), row);
+
+        let mut map = BTreeMap::new();
+        map.insert("name", "Felix");
+        let n: i64 = db.query_row(
+            "SELECT count(*) FROM pet WHERE name = :name",
+            named_params_from(&map),
+            |r| r.get(0),
+        )?;
+        assert_eq!(1, n);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unmatched() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let mut stmt = db.prepare("SELECT :name, :other")?;
+        assert_eq!(
+            Err(Error::InvalidParameterName(":other".to_owned())),
+            stmt.query_row(named_params_from(&FELIX), |_| Ok(()))
+        );
+        let name: String = stmt.query_row(
+            named_params_from(&FELIX).missing_params(Unmatched::Ignore),
+            |r| r.get(0),
+        )?;
+        assert_eq!("Felix", name);
+        let mut stmt = db.prepare("SELECT :name, ?")?;
+        assert_eq!(
+            Err(Error::InvalidParameterName("?2".to_owned())),
+            stmt.query_row(named_params_from(&FELIX), |_| Ok(()))
+        );
+
+        let mut stmt = db.prepare("SELECT :name")?;
+        assert_eq!(
+            Err(Error::InvalidParameterName(":id".to_owned())),
+            stmt.query_row(
+                named_params_from(&FELIX).unused_fields(Unmatched::Error),
+                |_| Ok(())
+            )
+        );
+        assert!(stmt.query_row(named_params_from(&1), |_| Ok(())).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json_field() -> Result<()> {
+        #[derive(Serialize)]
+        struct Drawing {
+            tags: Vec<&'static str>,
+        }
+        let db = Connection::open_in_memory()?;
+        let tags: String = db.query_row(
+            "SELECT :tags",
+            named_params_from(&Drawing {
+                tags: vec!["a", "b"],
+            }),
+            |r| r.get(0),
+        )?;
+        assert_eq!(r#"["a","b"]"#, tags);
+        Ok(())
+    }
+}
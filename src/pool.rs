@@ -0,0 +1,425 @@
+//! Pool of connections to the same database.
+//!
+//! All the connections of a [`Pool`] are opened with the same path and
+//! [`OpenFlags`], and are set up by the same initializer (to register
+//! functions, collations, set pragmas...) before being handed out.
+//!
+//! ```rust,no_run
+//! # use rusqlite::pool::Pool;
+//! # use rusqlite::Result;
+//! # use std::time::Duration;
+//! fn count_people() -> Result<i64> {
+//!     let pool = Pool::builder()
+//!         .max_size(4)
+//!         .connection_timeout(Duration::from_secs(5))
+//!         .init(|conn| conn.execute_batch("PRAGMA foreign_keys = ON;"))
+//!         .build("people.db")?;
+//!     let conn = pool.get()?;
+//!     conn.query_row("SELECT count(*) FROM people", [], |r| r.get(0))
+//! }
+//! ```
+//!
+//! Note that each connection to `:memory:` opens a distinct database.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::error::error_from_sqlite_code;
+use crate::ffi;
+use crate::{Connection, OpenFlags, Result};
+
+type Init = dyn Fn(&mut Connection) -> Result<()> + Send + Sync;
+
+/// Builder of a [`Pool`].
+pub struct PoolBuilder {
+    max_size: usize,
+    connection_timeout: Duration,
+    flags: OpenFlags,
+    init: Option<Box<Init>>,
+}
+
+impl PoolBuilder {
+    /// Maximum number of connections managed by the pool.
+    ///
+    /// 10 by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_size` is 0.
+    #[must_use]
+    pub fn max_size(mut self, max_size: usize) -> PoolBuilder {
+        assert!(max_size > 0, "max_size must be positive");
+        self.max_size = max_size;
+        self
+    }
+
+    /// Maximum time [`Pool::get`] waits for a connection to be available.
+    ///
+    /// 30 seconds by default.
+    #[must_use]
+    pub fn connection_timeout(mut self, timeout: Duration) -> PoolBuilder {
+        self.connection_timeout = timeout;
+        self
+    }
+
+    /// Flags used to open connections.
+    ///
+    /// `OpenFlags::default()` by default.
+    #[must_use]
+    pub fn flags(mut self, flags: OpenFlags) -> PoolBuilder {
+        self.flags = flags;
+        self
+    }
+
+    /// Function called on each new connection before it is handed out.
+    #[must_use]
+    pub fn init<F>(mut self, init: F) -> PoolBuilder
+    where
+        F: Fn(&mut Connection) -> Result<()> + Send + Sync + 'static,
+    {
+        self.init = Some(Box::new(init));
+        self
+    }
+
+    /// Create a pool of connections to the database at `path`.
+    ///
+    /// One connection is opened immediately so that an invalid path or a
+    /// failing initializer is reported here.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the connection cannot be opened or if the
+    /// initializer fails.
+    pub fn build<P: AsRef<Path>>(self, path: P) -> Result<Pool> {
+        let shared = Shared {
+            path: path.as_ref().to_path_buf(),
+            flags: self.flags,
+            init: self.init,
+            max_size: self.max_size,
+            connection_timeout: self.connection_timeout,
+            state: Mutex::new(State {
+                idle: Vec::new(),
+                size: 1,
+            }),
+            available: Condvar::new(),
+        };
+        let conn = shared.connect()?;
+        shared.lock().idle.push(conn);
+        Ok(Pool {
+            shared: Arc::new(shared),
+        })
+    }
+}
+
+impl fmt::Debug for PoolBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolBuilder")
+            .field("max_size", &self.max_size)
+            .field("connection_timeout", &self.connection_timeout)
+            .field("flags", &self.flags)
+            .finish()
+    }
+}
+
+struct State {
+    idle: Vec<Connection>,
+    // number of connections opened (or being opened), idle or not
+    size: usize,
+}
+
+struct Shared {
+    path: PathBuf,
+    flags: OpenFlags,
+    init: Option<Box<Init>>,
+    max_size: usize,
+    connection_timeout: Duration,
+    state: Mutex<State>,
+    available: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A panic cannot leave the state inconsistent.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        let mut conn = Connection::open_with_flags(&self.path, self.flags)?;
+        if let Some(ref init) = self.init {
+            init(&mut conn)?;
+        }
+        Ok(conn)
+    }
+
+    // Forget a connection which has been (or could not be) opened.
+    fn release_slot(&self) {
+        self.lock().size -= 1;
+        self.available.notify_one();
+    }
+}
+
+/// Pool of connections.
+///
+/// Cloning a `Pool` is cheap: clones share the same connections.
+#[derive(Clone)]
+pub struct Pool {
+    shared: Arc<Shared>,
+}
+
+impl Pool {
+    /// Start configuring a new pool.
+    pub fn builder() -> PoolBuilder {
+        PoolBuilder {
+            max_size: 10,
+            connection_timeout: Duration::from_secs(30),
+            flags: OpenFlags::default(),
+            init: None,
+        }
+    }
+
+    /// Create a pool of connections to the database at `path` with the
+    /// default settings.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the connection cannot be opened.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Pool> {
+        Pool::builder().build(path)
+    }
+
+    /// Check out a connection, waiting at most for the configured
+    /// [`connection_timeout`](PoolBuilder::connection_timeout).
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` with an `SQLITE_BUSY` code on timeout, or if a new
+    /// connection cannot be opened.
+    pub fn get(&self) -> Result<PooledConnection> {
+        self.get_timeout(self.shared.connection_timeout)
+    }
+
+    /// Check out a connection, waiting at most for `timeout`.
+    ///
+    /// Idle connections which are not in autocommit mode (i.e. which are
+    /// inside a transaction) are discarded.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` with an `SQLITE_BUSY` code on timeout, or if a new
+    /// connection cannot be opened.
+    pub fn get_timeout(&self, timeout: Duration) -> Result<PooledConnection> {
+        let shared = &self.shared;
+        let deadline = Instant::now() + timeout;
+        let mut state = shared.lock();
+        loop {
+            while let Some(conn) = state.idle.pop() {
+                if conn.is_autocommit() {
+                    return Ok(PooledConnection {
+                        conn: Some(conn),
+                        pool: self.clone(),
+                    });
+                }
+                state.size -= 1;
+            }
+            if state.size < shared.max_size {
+                state.size += 1;
+                drop(state);
+                return match shared.connect() {
+                    Ok(conn) => Ok(PooledConnection {
+                        conn: Some(conn),
+                        pool: self.clone(),
+                    }),
+                    Err(err) => {
+                        shared.release_slot();
+                        Err(err)
+                    }
+                };
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(error_from_sqlite_code(
+                    ffi::SQLITE_BUSY,
+                    Some("timed out waiting for a pooled connection".to_owned()),
+                ));
+            }
+            state = shared
+                .available
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Number of connections currently opened, idle or checked out.
+    pub fn size(&self) -> usize {
+        self.shared.lock().size
+    }
+
+    /// Number of idle connections.
+    pub fn idle_connections(&self) -> usize {
+        self.shared.lock().idle.len()
+    }
+
+    /// Maximum number of connections.
+    pub fn max_size(&self) -> usize {
+        self.shared.max_size
+    }
+
+    fn put_back(&self, conn: Connection) {
+        if conn.is_autocommit() {
+            self.shared.lock().idle.push(conn);
+            self.shared.available.notify_one();
+        } else {
+            // Left inside a transaction.
+            drop(conn);
+            self.shared.release_slot();
+        }
+    }
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.shared.lock();
+        f.debug_struct("Pool")
+            .field("path", &self.shared.path)
+            .field("max_size", &self.shared.max_size)
+            .field("size", &state.size)
+            .field("idle", &state.idle.len())
+            .finish()
+    }
+}
+
+/// Connection checked out of a [`Pool`].
+///
+/// The connection goes back to the pool when dropped, unless it has been left
+/// inside a transaction, in which case it is closed.
+pub struct PooledConnection {
+    conn: Option<Connection>,
+    pool: Pool,
+}
+
+impl PooledConnection {
+    /// Close the connection instead of returning it to the pool.
+    pub fn discard(mut self) {
+        if self.conn.take().is_some() {
+            self.pool.shared.release_slot();
+        }
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    #[inline]
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConnection {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.put_back(conn);
+        }
+    }
+}
+
+impl fmt::Debug for PooledConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledConnection")
+            .field("conn", &self.conn)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::Pool;
+    use crate::{Error, ErrorCode, Result};
+
+    #[test]
+    fn test_init() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let count = Arc::new(AtomicUsize::new(0));
+        let init_count = count.clone();
+        let pool = Pool::builder()
+            .max_size(2)
+            .init(move |conn| {
+                init_count.fetch_add(1, Ordering::SeqCst);
+                conn.execute_batch("PRAGMA foreign_keys = ON;")
+            })
+            .build(temp_dir.path().join("test.db3"))?;
+        let c1 = pool.get()?;
+        let c2 = pool.get()?;
+        assert_eq!(2, count.load(Ordering::SeqCst));
+        let fk: bool = c2.query_row("PRAGMA foreign_keys", [], |r| r.get(0))?;
+        assert!(fk);
+        drop(c1);
+        drop(c2);
+        assert_eq!(2, pool.idle_connections());
+        // idle connections are reused
+        let _c = pool.get()?;
+        assert_eq!(2, count.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[test]
+    fn test_timeout() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(temp_dir.path().join("test.db3"))?;
+        let c = pool.get()?;
+        match pool.get_timeout(Duration::from_millis(10)) {
+            Err(Error::SqliteFailure(err, _)) => assert_eq!(ErrorCode::DatabaseBusy, err.code),
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        let other = pool.clone();
+        let handle = thread::spawn(move || other.get_timeout(Duration::from_secs(10)).is_ok());
+        thread::sleep(Duration::from_millis(10));
+        drop(c);
+        assert!(handle.join().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_discard_in_transaction() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(temp_dir.path().join("test.db3"))?;
+        {
+            let c = pool.get()?;
+            c.execute_batch("BEGIN; CREATE TABLE foo(x);")?;
+        }
+        assert_eq!(0, pool.size());
+        let c = pool.get()?;
+        assert!(c.is_autocommit());
+        // the transaction has been rolled back
+        let n: i64 = c.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE name = 'foo'",
+            [],
+            |r| r.get(0),
+        )?;
+        assert_eq!(0, n);
+        c.discard();
+        assert_eq!(0, pool.size());
+        Ok(())
+    }
+}
@@ -0,0 +1,453 @@
+//! Typed accessors of the well-known pragmas
+//!
+//! The setters read the value back, and return
+//! [`Error::PragmaNotApplied`] when SQLite silently ignored it.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Error, Result};
+//! use rusqlite::pragmas::{JournalMode, TempStore};
+//!
+//! fn setup(conn: &Connection) -> Result<()> {
+//!     let pragmas = conn.pragmas();
+//!     pragmas.set_temp_store(TempStore::Memory)?;
+//!     pragmas.set_cache_size(-64 * 1024)?;
+//!     match pragmas.set_journal_mode(JournalMode::Wal) {
+//!         // not supported by in-memory databases
+//!         Err(Error::PragmaNotApplied(..)) => assert_eq!(JournalMode::Memory, pragmas.journal_mode()?),
+//!         r => r?,
+//!     }
+//!     Ok(())
+//! }
+//! # fn main() { setup(&Connection::open_in_memory().unwrap()).unwrap(); }
+//! ```
+
+use crate::types::{FromSql, FromSqlError, FromSqlResult, ToSql, Value, ValueRef};
+use crate::{Connection, DatabaseName, Error, OptionalExtension, Result};
+
+pub use crate::options::{JournalMode, Synchronous};
+
+/// Storage of temporary tables and indexes, see [`PRAGMA temp_store`](https://sqlite.org/pragma.html#pragma_temp_store).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TempStore {
+    /// Equivalent to `DEFAULT`: chosen by the `SQLITE_TEMP_STORE`
+    /// compile-time option
+    Default,
+    /// Equivalent to `FILE`
+    File,
+    /// Equivalent to `MEMORY`
+    Memory,
+}
+
+impl TempStore {
+    /// Keyword of the temporary storage, as used by `PRAGMA temp_store`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TempStore::Default => "DEFAULT",
+            TempStore::File => "FILE",
+            TempStore::Memory => "MEMORY",
+        }
+    }
+}
+
+impl FromSql for TempStore {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(TempStore::Default),
+            1 => Ok(TempStore::File),
+            2 => Ok(TempStore::Memory),
+            n => Err(FromSqlError::OutOfRange(n)),
+        }
+    }
+}
+
+/// Locking mode, see [`PRAGMA locking_mode`](https://sqlite.org/pragma.html#pragma_locking_mode).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LockingMode {
+    /// Equivalent to `NORMAL`
+    Normal,
+    /// Equivalent to `EXCLUSIVE`
+    Exclusive,
+}
+
+impl LockingMode {
+    /// Keyword of the locking mode, as used by `PRAGMA locking_mode`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LockingMode::Normal => "NORMAL",
+            LockingMode::Exclusive => "EXCLUSIVE",
+        }
+    }
+}
+
+impl FromSql for LockingMode {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        if s.eq_ignore_ascii_case("normal") {
+            Ok(LockingMode::Normal)
+        } else if s.eq_ignore_ascii_case("exclusive") {
+            Ok(LockingMode::Exclusive)
+        } else {
+            Err(FromSqlError::Other(
+                format!("invalid locking mode: {}", s).into(),
+            ))
+        }
+    }
+}
+
+/// Auto-vacuum mode, see [`PRAGMA auto_vacuum`](https://sqlite.org/pragma.html#pragma_auto_vacuum).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AutoVacuum {
+    /// Equivalent to `NONE`
+    None,
+    /// Equivalent to `FULL`
+    Full,
+    /// Equivalent to `INCREMENTAL`
+    Incremental,
+}
+
+impl AutoVacuum {
+    /// Keyword of the auto-vacuum mode, as used by `PRAGMA auto_vacuum`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AutoVacuum::None => "NONE",
+            AutoVacuum::Full => "FULL",
+            AutoVacuum::Incremental => "INCREMENTAL",
+        }
+    }
+}
+
+impl FromSql for AutoVacuum {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(AutoVacuum::None),
+            1 => Ok(AutoVacuum::Full),
+            2 => Ok(AutoVacuum::Incremental),
+            n => Err(FromSqlError::OutOfRange(n)),
+        }
+    }
+}
+
+/// Secure delete mode, see [`PRAGMA secure_delete`](https://sqlite.org/pragma.html#pragma_secure_delete).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SecureDelete {
+    /// Equivalent to `OFF`
+    Off,
+    /// Equivalent to `ON`
+    On,
+    /// Equivalent to `FAST` (SQLite 3.20.0 and later)
+    Fast,
+}
+
+impl SecureDelete {
+    /// Keyword of the secure delete mode, as used by `PRAGMA secure_delete`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SecureDelete::Off => "OFF",
+            SecureDelete::On => "ON",
+            SecureDelete::Fast => "FAST",
+        }
+    }
+}
+
+impl FromSql for SecureDelete {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(SecureDelete::Off),
+            1 => Ok(SecureDelete::On),
+            2 => Ok(SecureDelete::Fast),
+            n => Err(FromSqlError::OutOfRange(n)),
+        }
+    }
+}
+
+/// Typed accessors of the well-known pragmas, see [`Connection::pragmas`].
+///
+/// The getters read the value of the `main` database, and the setters
+/// change the value of all the databases, unless a database is selected
+/// with [`Pragmas::database`].
+#[derive(Clone, Copy, Debug)]
+pub struct Pragmas<'conn> {
+    conn: &'conn Connection,
+    db_name: Option<DatabaseName<'conn>>,
+}
+
+impl<'conn> Pragmas<'conn> {
+    /// Get and set the pragmas of `db_name` only.
+    ///
+    /// `temp_store` is a property of the connection and ignores it.
+    #[inline]
+    pub fn database(self, db_name: DatabaseName<'conn>) -> Pragmas<'conn> {
+        Pragmas {
+            db_name: Some(db_name),
+            ..self
+        }
+    }
+
+    /// Get `PRAGMA journal_mode`.
+    #[inline]
+    pub fn journal_mode(&self) -> Result<JournalMode> {
+        self.get(self.db_name, "journal_mode")
+    }
+
+    /// Set `PRAGMA journal_mode`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err(Error::PragmaNotApplied)` if the mode is not
+    /// supported, e.g. `WAL` by an in-memory database.
+    #[inline]
+    pub fn set_journal_mode(&self, mode: JournalMode) -> Result<()> {
+        self.set(self.db_name, "journal_mode", &mode.as_str(), mode)
+    }
+
+    /// Get `PRAGMA synchronous`.
+    #[inline]
+    pub fn synchronous(&self) -> Result<Synchronous> {
+        self.get(self.db_name, "synchronous")
+    }
+
+    /// Set `PRAGMA synchronous`.
+    #[inline]
+    pub fn set_synchronous(&self, synchronous: Synchronous) -> Result<()> {
+        self.set(
+            self.db_name,
+            "synchronous",
+            &synchronous.as_str(),
+            synchronous,
+        )
+    }
+
+    /// Get `PRAGMA temp_store`.
+    #[inline]
+    pub fn temp_store(&self) -> Result<TempStore> {
+        self.get(None, "temp_store")
+    }
+
+    /// Set `PRAGMA temp_store`.
+    #[inline]
+    pub fn set_temp_store(&self, temp_store: TempStore) -> Result<()> {
+        self.set(None, "temp_store", &temp_store.as_str(), temp_store)
+    }
+
+    /// Get `PRAGMA locking_mode`.
+    #[inline]
+    pub fn locking_mode(&self) -> Result<LockingMode> {
+        self.get(self.db_name, "locking_mode")
+    }
+
+    /// Set `PRAGMA locking_mode`.
+    #[inline]
+    pub fn set_locking_mode(&self, mode: LockingMode) -> Result<()> {
+        self.set(self.db_name, "locking_mode", &mode.as_str(), mode)
+    }
+
+    /// Get `PRAGMA auto_vacuum`.
+    #[inline]
+    pub fn auto_vacuum(&self) -> Result<AutoVacuum> {
+        self.get(self.db_name, "auto_vacuum")
+    }
+
+    /// Set `PRAGMA auto_vacuum`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err(Error::PragmaNotApplied)` if the mode cannot be
+    /// changed, i.e. between `NONE` and `FULL` or `INCREMENTAL` once tables
+    /// have been created, until the next `VACUUM`.
+    #[inline]
+    pub fn set_auto_vacuum(&self, mode: AutoVacuum) -> Result<()> {
+        self.set(self.db_name, "auto_vacuum", &mode.as_str(), mode)
+    }
+
+    /// Get `PRAGMA secure_delete`.
+    #[inline]
+    pub fn secure_delete(&self) -> Result<SecureDelete> {
+        self.get(self.db_name, "secure_delete")
+    }
+
+    /// Set `PRAGMA secure_delete`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err(Error::PragmaNotApplied)` if the mode is not
+    /// supported, e.g. `FAST` before SQLite 3.20.0.
+    #[inline]
+    pub fn set_secure_delete(&self, mode: SecureDelete) -> Result<()> {
+        self.set(self.db_name, "secure_delete", &mode.as_str(), mode)
+    }
+
+    /// Get `PRAGMA cache_size`: a number of pages if positive, or of KiB if
+    /// negative.
+    #[inline]
+    pub fn cache_size(&self) -> Result<i64> {
+        self.get(self.db_name, "cache_size")
+    }
+
+    /// Set `PRAGMA cache_size`: a number of pages if positive, or of KiB if
+    /// negative.
+    #[inline]
+    pub fn set_cache_size(&self, size: i64) -> Result<()> {
+        self.set(self.db_name, "cache_size", &size, size)
+    }
+
+    /// Get `PRAGMA page_size`, in bytes.
+    #[inline]
+    pub fn page_size(&self) -> Result<u32> {
+        self.get(self.db_name, "page_size")
+    }
+
+    /// Set `PRAGMA page_size`, in bytes.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err(Error::PragmaNotApplied)` if the size is invalid or
+    /// if the database already exists, until the next `VACUUM`.
+    #[inline]
+    pub fn set_page_size(&self, size: u32) -> Result<()> {
+        self.set(self.db_name, "page_size", &size, size)
+    }
+
+    /// Get `PRAGMA mmap_size`, in bytes.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err(Error::QueryReturnedNoRows)` for an in-memory
+    /// database.
+    #[inline]
+    pub fn mmap_size(&self) -> Result<u64> {
+        self.get(self.db_name, "mmap_size")
+    }
+
+    /// Set `PRAGMA mmap_size`, in bytes.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err(Error::PragmaNotApplied)` if the size is greater than
+    /// the `SQLITE_MAX_MMAP_SIZE` compile-time option or for an in-memory
+    /// database.
+    #[inline]
+    pub fn set_mmap_size(&self, size: u64) -> Result<()> {
+        self.set(self.db_name, "mmap_size", &size, size)
+    }
+
+    fn get<T: FromSql>(&self, db_name: Option<DatabaseName<'_>>, name: &str) -> Result<T> {
+        self.conn
+            .pragma_query_value(db_name, name, |row| row.get(0))
+    }
+
+    fn set<T: FromSql + PartialEq>(
+        &self,
+        db_name: Option<DatabaseName<'_>>,
+        name: &str,
+        value: &dyn ToSql,
+        expected: T,
+    ) -> Result<()> {
+        // some pragmas (e.g. journal_mode) return the new value
+        self.conn.pragma(db_name, name, value, |_| Ok(()))?;
+        let actual: Option<Value> = self.get(db_name, name).optional()?;
+        let applied = actual
+            .as_ref()
+            .and_then(|v| T::column_result(ValueRef::from(v)).ok())
+            .filter(|v| *v == expected)
+            .is_some();
+        if applied {
+            return Ok(());
+        }
+        let actual = match actual {
+            None | Some(Value::Null) => "NULL".to_owned(),
+            Some(Value::Integer(i)) => i.to_string(),
+            Some(Value::Real(f)) => f.to_string(),
+            Some(Value::Text(s)) => s,
+            Some(Value::Blob(_)) => "BLOB".to_owned(),
+        };
+        Err(Error::PragmaNotApplied(name.to_owned(), actual))
+    }
+}
+
+impl Connection {
+    /// Typed accessors of the well-known pragmas.
+    #[inline]
+    pub fn pragmas(&self) -> Pragmas<'_> {
+        Pragmas {
+            conn: self,
+            db_name: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AutoVacuum, JournalMode, LockingMode, SecureDelete, Synchronous, TempStore};
+    use crate::{Connection, Error, Result, MAIN_DB};
+
+    #[test]
+    fn test_enums() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let pragmas = db.pragmas();
+        pragmas.set_synchronous(Synchronous::Normal)?;
+        assert_eq!(Synchronous::Normal, pragmas.synchronous()?);
+        pragmas.set_temp_store(TempStore::Memory)?;
+        assert_eq!(TempStore::Memory, pragmas.temp_store()?);
+        pragmas.set_locking_mode(LockingMode::Exclusive)?;
+        assert_eq!(LockingMode::Exclusive, pragmas.locking_mode()?);
+        pragmas.set_secure_delete(SecureDelete::Off)?;
+        assert_eq!(SecureDelete::Off, pragmas.secure_delete()?);
+        pragmas.set_auto_vacuum(AutoVacuum::Incremental)?;
+        assert_eq!(AutoVacuum::Incremental, pragmas.auto_vacuum()?);
+
+        db.execute_batch("CREATE TABLE foo(x)")?;
+        assert_eq!(
+            Err(Error::PragmaNotApplied(
+                "auto_vacuum".to_owned(),
+                "2".to_owned()
+            )),
+            pragmas.set_auto_vacuum(AutoVacuum::None)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal_mode() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let pragmas = db.pragmas().database(MAIN_DB);
+        assert_eq!(JournalMode::Memory, pragmas.journal_mode()?);
+        assert_eq!(
+            Err(Error::PragmaNotApplied(
+                "journal_mode".to_owned(),
+                "memory".to_owned()
+            )),
+            pragmas.set_journal_mode(JournalMode::Wal)
+        );
+        pragmas.set_journal_mode(JournalMode::Off)?;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Connection::open(temp_dir.path().join("test.db3"))?;
+        let pragmas = db.pragmas();
+        pragmas.set_page_size(8192)?;
+        pragmas.set_journal_mode(JournalMode::Wal)?;
+        assert_eq!(JournalMode::Wal, pragmas.journal_mode()?);
+        assert_eq!(8192, pragmas.page_size()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sizes() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let pragmas = db.pragmas();
+        pragmas.set_cache_size(-1024)?;
+        assert_eq!(-1024, pragmas.cache_size()?);
+        assert!(matches!(
+            pragmas.set_page_size(1000),
+            Err(Error::PragmaNotApplied(..))
+        ));
+        assert!(matches!(
+            pragmas.set_mmap_size(1 << 20),
+            Err(Error::PragmaNotApplied(..))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+//! Queries checked at compile time, see [`query!`](crate::query!)
+//!
+//! The `query!` macro prepares its SQL against an in-memory database built
+//! from the `schema.sql` file at the root of the crate being compiled (or
+//! from the file named by the `RUSQLITE_SCHEMA` environment variable,
+//! relative to the root of the crate). SQL which fails to prepare, or which
+//! expects another number of parameters, is rejected at compile time.
+//!
+//! The SQL is prepared with the bundled SQLite (the `query_macro` feature
+//! enables `libsqlite3-sys/bundled` for the macro), so the checks don't
+//! depend on the SQLite installed on the build host.
+//!
+//! With `resolver = "2"` (the default from edition 2021), the bundled SQLite
+//! is only linked into the macro. With the older resolver, Cargo unifies the
+//! features of `libsqlite3-sys`, so enabling `query_macro` links the bundled
+//! SQLite into your binary too, in place of `sqlcipher`, `winsqlite3` or the
+//! system SQLite.
+//!
+//! The macro evaluates to a [`Query`] whose rows are instances of an
+//! anonymous struct with one field per column of the result. The type of each
+//! field is derived from the declared type of the column, following the
+//! [affinity rules](https://sqlite.org/datatype3.html#determination_of_column_affinity)
+//! of SQLite:
+//!
+//! | Declared type | Rust type |
+//! |---------------|-----------|
+//! | contains `INT` | `i64` |
+//! | contains `CHAR`, `CLOB` or `TEXT` | `String` |
+//! | contains `BLOB` | `Vec<u8>` |
+//! | contains `REAL`, `FLOA` or `DOUB` | `f64` |
+//! | contains `BOOL` | `bool` |
+//! | none (e.g. an expression) or anything else | [`Value`](crate::types::Value) |
+//!
+//! Fields are `Option`s, except for [`Value`](crate::types::Value) (which
+//! has a `Null` variant) and for the columns of a table declared `NOT NULL`
+//! or `INTEGER PRIMARY KEY`, as reported by
+//! [`sqlite3_table_column_metadata`](https://sqlite.org/c3ref/table_column_metadata.html).
+//! As such a column can still be `NULL` on the outer side of a join, no
+//! column is considered `NOT NULL` in a statement with an outer join. The
+//! inferred nullability can be overridden by ending the name of the column
+//! with `!` (not null) or `?` (nullable), e.g.
+//! `SELECT age AS "age!" FROM person WHERE age IS NOT NULL`.
+//!
+//! ```rust,ignore
+//! use rusqlite::{query, Connection, Result};
+//!
+//! fn adults(conn: &Connection, age: i64) -> Result<Vec<String>> {
+//!     let people = query!("SELECT name, age FROM person WHERE age >= ?", age)
+//!         .fetch_all(conn)?;
+//!     Ok(people.into_iter().map(|p| p.name).collect())
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::{Connection, OptionalExtension, Result, Row, ToSql};
+
+/// A statement and its parameters, built by [`query!`](crate::query!).
+pub struct Query<'a, T> {
+    sql: &'static str,
+    params: Vec<Box<dyn ToSql + 'a>>,
+    map: fn(&Row<'_>) -> Result<T>,
+}
+
+impl<'a, T> Query<'a, T> {
+    /// Query executing `sql` and converting each row with `map`.
+    pub fn new(sql: &'static str, map: fn(&Row<'_>) -> Result<T>) -> Query<'a, T> {
+        Query {
+            sql,
+            params: Vec::new(),
+            map,
+        }
+    }
+
+    /// Add the value of the next positional parameter.
+    pub fn arg<P: ToSql + 'a>(mut self, param: P) -> Query<'a, T> {
+        self.params.push(Box::new(param));
+        self
+    }
+
+    /// SQL of the query.
+    pub fn sql(&self) -> &'static str {
+        self.sql
+    }
+
+    /// Execute the query and return all the rows.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the statement cannot be prepared, if binding the
+    /// parameters fails or if a column cannot be converted.
+    pub fn fetch_all(&self, conn: &Connection) -> Result<Vec<T>> {
+        let mut stmt = conn.prepare_cached(self.sql)?;
+        let rows = stmt.query_map(self.params(), self.map)?;
+        rows.collect()
+    }
+
+    /// Execute the query and return its first row.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err(QueryReturnedNoRows)` if the query returns no rows,
+    /// or the errors of [`Query::fetch_all`].
+    pub fn fetch_one(&self, conn: &Connection) -> Result<T> {
+        let mut stmt = conn.prepare_cached(self.sql)?;
+        stmt.query_row(self.params(), self.map)
+    }
+
+    /// Execute the query and return its first row, if any.
+    ///
+    /// # Failure
+    ///
+    /// See [`Query::fetch_all`].
+    pub fn fetch_optional(&self, conn: &Connection) -> Result<Option<T>> {
+        self.fetch_one(conn).optional()
+    }
+
+    /// Execute a statement which returns no rows, and return the number of
+    /// rows that were changed, inserted or deleted.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the statement cannot be prepared, if binding the
+    /// parameters fails or if the statement returns rows.
+    pub fn execute(&self, conn: &Connection) -> Result<usize> {
+        let mut stmt = conn.prepare_cached(self.sql)?;
+        stmt.execute(self.params())
+    }
+
+    fn params(&self) -> crate::ParamsFromIter<impl Iterator<Item = &(dyn ToSql + 'a)>> {
+        crate::params_from_iter(self.params.iter().map(|p| &**p))
+    }
+}
+
+impl<T> fmt::Debug for Query<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Query")
+            .field("sql", &self.sql)
+            .field("params", &self.params.len())
+            .finish()
+    }
+}
@@ -83,6 +83,24 @@ impl RawStatement {
         }
     }
 
+    #[inline]
+    #[cfg(feature = "column_metadata")]
+    pub fn column_database_name(&self, idx: usize) -> Option<&CStr> {
+        unsafe { optional_cstr(ffi::sqlite3_column_database_name(self.ptr, idx as c_int)) }
+    }
+
+    #[inline]
+    #[cfg(feature = "column_metadata")]
+    pub fn column_table_name(&self, idx: usize) -> Option<&CStr> {
+        unsafe { optional_cstr(ffi::sqlite3_column_table_name(self.ptr, idx as c_int)) }
+    }
+
+    #[inline]
+    #[cfg(feature = "column_metadata")]
+    pub fn column_origin_name(&self, idx: usize) -> Option<&CStr> {
+        unsafe { optional_cstr(ffi::sqlite3_column_origin_name(self.ptr, idx as c_int)) }
+    }
+
     #[inline]
     pub fn column_name(&self, idx: usize) -> Option<&CStr> {
         let idx = idx as c_int;
@@ -218,3 +236,12 @@ impl Drop for RawStatement {
         self.finalize_();
     }
 }
+
+#[cfg(feature = "column_metadata")]
+unsafe fn optional_cstr<'a>(p: *const std::os::raw::c_char) -> Option<&'a CStr> {
+    if p.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(p))
+    }
+}
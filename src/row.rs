@@ -3,7 +3,11 @@ use fallible_streaming_iterator::FallibleStreamingIterator;
 use std::convert;
 
 use super::{Error, Result, Statement};
-use crate::types::{FromSql, FromSqlError, ValueRef};
+use crate::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+mod serde;
 
 /// An handle for the resulting rows of a query.
 #[must_use = "Rows is lazy and will do nothing unless consumed"]
@@ -278,9 +282,38 @@ impl<'stmt> Row<'stmt> {
     /// enabled), and the underlying SQLite column is a blob whose size is not
     /// 16 bytes, `Error::InvalidColumnType` will also be returned.
     pub fn get<I: RowIndex, T: FromSql>(&self, idx: I) -> Result<T> {
+        self.get_with(idx, T::column_result)
+    }
+
+    /// Get the value of a particular column of the result row, converted by
+    /// `f` instead of the `FromSql` implementation of `T`.
+    ///
+    /// ```rust
+    /// # use rusqlite::{Connection, Result};
+    /// use rusqlite::types::{FromSqlResult, ValueRef};
+    ///
+    /// fn yes_no(value: ValueRef<'_>) -> FromSqlResult<bool> {
+    ///     value.as_str().map(|s| s == "yes")
+    /// }
+    ///
+    /// fn is_admin(conn: &Connection) -> Result<bool> {
+    ///     conn.query_row("SELECT 'yes'", [], |row| row.get_with(0, yes_no))
+    /// }
+    /// # fn main() { assert!(is_admin(&Connection::open_in_memory().unwrap()).unwrap()); }
+    /// ```
+    ///
+    /// ## Failure
+    ///
+    /// Returns the same errors as [`get`](Row::get), with the conversion
+    /// errors returned by `f`.
+    pub fn get_with<I, T, F>(&self, idx: I, f: F) -> Result<T>
+    where
+        I: RowIndex,
+        F: FnOnce(ValueRef<'_>) -> FromSqlResult<T>,
+    {
         let idx = idx.idx(self.stmt)?;
         let value = self.stmt.value_ref(idx);
-        FromSql::column_result(value).map_err(|err| match err {
+        f(value).map_err(|err| match err {
             FromSqlError::InvalidType => Error::InvalidColumnType(
                 idx,
                 self.stmt.column_name_unwrap(idx).into(),
@@ -0,0 +1,717 @@
+//! Serde `Deserializer` over a [`Row`].
+use std::error;
+use std::fmt;
+
+use serde::de::value::{
+    BorrowedStrDeserializer, SeqDeserializer, StrDeserializer, U32Deserializer,
+};
+use serde::de::{
+    self, Deserialize, DeserializeOwned, DeserializeSeed, IntoDeserializer, Unexpected, Visitor,
+};
+
+use crate::error::UNKNOWN_COLUMN;
+use crate::types::{Type, ValueRef};
+use crate::{Error, MappedRows, Params, Result, Row, Statement};
+
+impl Row<'_> {
+    /// Deserialize the row into `T`.
+    ///
+    /// Structs and maps are filled column by column, using the column names
+    /// as keys (unknown columns are ignored, and missing `Option` fields are
+    /// `None`), tuples and sequences by column index. Any other type is
+    /// deserialized from the only column of the row.
+    ///
+    /// In a column:
+    /// * `NULL` is deserialized as `None` or `()`,
+    /// * newtype structs are deserialized from the wrapped value,
+    /// * field-less enums are stored either as text (the name of the variant)
+    ///   or as an integer (the index of the variant),
+    /// * with the `serde_json` feature, structs, maps, sequences and
+    ///   data-carrying enums are stored as JSON text.
+    ///
+    /// ```rust,no_run
+    /// # use rusqlite::{Connection, Result};
+    /// # use serde::Deserialize;
+    /// #[derive(Deserialize)]
+    /// struct Person {
+    ///     id: i64,
+    ///     name: String,
+    ///     email: Option<String>,
+    /// }
+    ///
+    /// fn get_person(conn: &Connection, id: i64) -> Result<Person> {
+    ///     conn.query_row("SELECT * FROM person WHERE id = ?", [id], |row| {
+    ///         row.deserialize()
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// ## Failure
+    ///
+    /// Returns an `Error::InvalidColumnName` if a field has no matching
+    /// column.
+    ///
+    /// Returns an `Error::InvalidColumnType` if the SQLite type of a column
+    /// doesn't match the type of its field, or an
+    /// `Error::IntegralValueOutOfRange` if an integer doesn't fit.
+    ///
+    /// Returns an `Error::FromSqlConversionFailure`, whose message contains the
+    /// name of the column, for any other error.
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T> {
+        T::deserialize(RowDeserializer { row: self }).map_err(|err| err.into_error(self))
+    }
+}
+
+impl Statement<'_> {
+    /// Execute the prepared statement, returning an iterator over the rows
+    /// deserialized into `T`, see [`Row::deserialize`].
+    ///
+    /// ```rust,no_run
+    /// # use rusqlite::{Connection, Result};
+    /// # use serde::Deserialize;
+    /// #[derive(Deserialize)]
+    /// struct Person {
+    ///     id: i64,
+    ///     name: String,
+    /// }
+    ///
+    /// fn get_people(conn: &Connection) -> Result<Vec<Person>> {
+    ///     let mut stmt = conn.prepare("SELECT id, name FROM person")?;
+    ///     let rows = stmt.query_as::<Person, _>([])?;
+    ///     rows.collect()
+    /// }
+    /// ```
+    ///
+    /// ## Failure
+    ///
+    /// Will return `Err` if binding parameters fails.
+    #[allow(clippy::type_complexity)]
+    pub fn query_as<T, P>(&mut self, params: P) -> Result<MappedRows<'_, fn(&Row<'_>) -> Result<T>>>
+    where
+        T: DeserializeOwned,
+        P: Params,
+    {
+        self.query_map(params, deserialize_row::<T> as fn(&Row<'_>) -> Result<T>)
+    }
+}
+
+fn deserialize_row<T: DeserializeOwned>(row: &Row<'_>) -> Result<T> {
+    row.deserialize()
+}
+
+#[derive(Debug)]
+enum Kind {
+    MissingField(&'static str),
+    InvalidType(String),
+    OutOfRange(i64),
+    Message(String),
+}
+
+/// Error of a [`Row::deserialize`] call, converted to an [`Error`] once the
+/// column is known.
+#[derive(Debug)]
+struct DeError {
+    column: Option<usize>,
+    kind: Kind,
+}
+
+impl DeError {
+    fn message<T: fmt::Display>(msg: T) -> DeError {
+        DeError {
+            column: None,
+            kind: Kind::Message(msg.to_string()),
+        }
+    }
+
+    fn at(mut self, column: usize) -> DeError {
+        self.column.get_or_insert(column);
+        self
+    }
+
+    fn into_error(self, row: &Row<'_>) -> Error {
+        let idx = match self.column {
+            Some(idx) => idx,
+            None => {
+                return match self.kind {
+                    Kind::MissingField(name) => Error::InvalidColumnName(name.to_owned()),
+                    _ => Error::FromSqlConversionFailure(
+                        UNKNOWN_COLUMN,
+                        Type::Null,
+                        Box::new(ColumnError {
+                            name: None,
+                            kind: self.kind,
+                        }),
+                    ),
+                }
+            }
+        };
+        let name = row.stmt.column_name_unwrap(idx);
+        let data_type = row.stmt.value_ref(idx).data_type();
+        match self.kind {
+            Kind::InvalidType(_) => Error::InvalidColumnType(idx, name.to_owned(), data_type),
+            Kind::OutOfRange(i) => Error::IntegralValueOutOfRange(idx, i),
+            kind => Error::FromSqlConversionFailure(
+                idx,
+                data_type,
+                Box::new(ColumnError {
+                    name: Some(name.to_owned()),
+                    kind,
+                }),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Kind::MissingField(name) => write!(f, "missing field `{}`", name),
+            Kind::InvalidType(ref msg) | Kind::Message(ref msg) => msg.fmt(f),
+            Kind::OutOfRange(i) => write!(f, "integer {} out of range", i),
+        }
+    }
+}
+
+impl error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(msg: T) -> DeError {
+        DeError::message(msg)
+    }
+
+    fn invalid_type(unexp: Unexpected<'_>, exp: &dyn de::Expected) -> DeError {
+        DeError {
+            column: None,
+            kind: Kind::InvalidType(format!("invalid type: {}, expected {}", unexp, exp)),
+        }
+    }
+
+    fn invalid_value(unexp: Unexpected<'_>, exp: &dyn de::Expected) -> DeError {
+        match unexp {
+            Unexpected::Signed(i) => DeError {
+                column: None,
+                kind: Kind::OutOfRange(i),
+            },
+            _ => DeError::message(format_args!("invalid value: {}, expected {}", unexp, exp)),
+        }
+    }
+
+    fn missing_field(field: &'static str) -> DeError {
+        DeError {
+            column: None,
+            kind: Kind::MissingField(field),
+        }
+    }
+}
+
+/// Conversion error of a column, reported through
+/// `Error::FromSqlConversionFailure`.
+#[derive(Debug)]
+struct ColumnError {
+    name: Option<String>,
+    kind: Kind,
+}
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "column `{}`: {}", name, self.kind),
+            None => self.kind.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ColumnError {}
+
+struct RowDeserializer<'de, 'stmt> {
+    row: &'de Row<'stmt>,
+}
+
+impl<'de> RowDeserializer<'de, '_> {
+    fn value(&self, idx: usize) -> ValueDeserializer<'de> {
+        ValueDeserializer {
+            value: self.row.stmt.value_ref(idx),
+        }
+    }
+
+    // Deserializer of the only column of the row
+    fn single_column(&self) -> Result<ValueDeserializer<'de>, DeError> {
+        match self.row.stmt.column_count() {
+            1 => Ok(self.value(0)),
+            n => Err(DeError::message(format_args!(
+                "expected a single column, got {}",
+                n
+            ))),
+        }
+    }
+}
+
+macro_rules! forward_to_single_column {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                self.single_column()?.$method(visitor).map_err(|e| e.at(0))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for RowDeserializer<'de, '_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    forward_to_single_column! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_option
+        deserialize_unit
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.single_column()?
+            .deserialize_unit_struct(name, visitor)
+            .map_err(|e| e.at(0))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_seq(Columns { de: self, idx: 0 })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_map(Columns { de: self, idx: 0 })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.single_column()?
+            .deserialize_enum(name, variants, visitor)
+            .map_err(|e| e.at(0))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+}
+
+/// Columns of a row, as a sequence of values or a map from column names to
+/// values.
+struct Columns<'de, 'stmt> {
+    de: RowDeserializer<'de, 'stmt>,
+    idx: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Columns<'de, '_> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError> {
+        if self.idx >= self.de.row.stmt.column_count() {
+            return Ok(None);
+        }
+        let idx = self.idx;
+        self.idx += 1;
+        seed.deserialize(self.de.value(idx))
+            .map(Some)
+            .map_err(|e| e.at(idx))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.de.row.stmt.column_count() - self.idx)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Columns<'de, '_> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        if self.idx >= self.de.row.stmt.column_count() {
+            return Ok(None);
+        }
+        let name = self.de.row.stmt.column_name_unwrap(self.idx);
+        let key: StrDeserializer<'_, DeError> = name.into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DeError> {
+        let idx = self.idx;
+        self.idx += 1;
+        seed.deserialize(self.de.value(idx)).map_err(|e| e.at(idx))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.de.row.stmt.column_count() - self.idx)
+    }
+}
+
+/// Deserializer of the value of one column.
+struct ValueDeserializer<'de> {
+    value: ValueRef<'de>,
+}
+
+// With the `serde_json` feature, deserialize a text value as JSON with
+// `$call`, otherwise (or for any other value) use `deserialize_any`.
+macro_rules! json_or_any {
+    ($self:ident, $visitor:ident, $json:ident => $call:expr) => {{
+        #[cfg(feature = "serde_json")]
+        {
+            if let ValueRef::Text(s) = $self.value {
+                let mut $json = serde_json::Deserializer::from_slice(s);
+                let value = $call.map_err(DeError::message)?;
+                $json.end().map_err(DeError::message)?;
+                return Ok(value);
+            }
+        }
+        $self.deserialize_any($visitor)
+    }};
+}
+
+// the arguments are only used to deserialize JSON
+#[cfg_attr(not(feature = "serde_json"), allow(unused_variables))]
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            ValueRef::Null => visitor.visit_unit(),
+            ValueRef::Integer(i) => visitor.visit_i64(i),
+            ValueRef::Real(f) => visitor.visit_f64(f),
+            ValueRef::Text(s) => match std::str::from_utf8(s) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(err) => Err(DeError::message(err)),
+            },
+            ValueRef::Blob(b) => visitor.visit_borrowed_bytes(b),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            ValueRef::Integer(i) => visitor.visit_bool(i != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            ValueRef::Text(b) | ValueRef::Blob(b) => visitor.visit_borrowed_bytes(b),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            ValueRef::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if let ValueRef::Blob(b) = self.value {
+            return visitor.visit_seq(SeqDeserializer::new(b.iter().copied()));
+        }
+        json_or_any!(self, visitor, json => de::Deserializer::deserialize_seq(&mut json, visitor))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        json_or_any!(self, visitor, json => de::Deserializer::deserialize_map(&mut json, visitor))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        json_or_any!(self, visitor, json => {
+            de::Deserializer::deserialize_tuple(&mut json, len, visitor)
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        json_or_any!(self, visitor, json => {
+            de::Deserializer::deserialize_tuple_struct(&mut json, name, len, visitor)
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        json_or_any!(self, visitor, json => {
+            de::Deserializer::deserialize_struct(&mut json, name, fields, visitor)
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        match self.value {
+            ValueRef::Integer(i) => {
+                if i < 0 || i > i64::from(u32::MAX) {
+                    return Err(DeError {
+                        column: None,
+                        kind: Kind::OutOfRange(i),
+                    });
+                }
+                let index: U32Deserializer<DeError> = (i as u32).into_deserializer();
+                visitor.visit_enum(index)
+            }
+            // data-carrying variants are stored as a JSON object
+            ValueRef::Text(s) if s.first() == Some(&b'{') => json_or_any!(self, visitor, json => {
+                de::Deserializer::deserialize_enum(&mut json, name, variants, visitor)
+            }),
+            ValueRef::Text(s) => match std::str::from_utf8(s) {
+                Ok(s) => visitor.visit_enum(BorrowedStrDeserializer::<DeError>::new(s)),
+                Err(err) => Err(DeError::message(err)),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string unit
+        unit_struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+
+    use crate::types::Type;
+    use crate::{Connection, Error, Result};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Id(i64);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Kind {
+        Cat,
+        Dog,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Pet {
+        id: Id,
+        name: String,
+        kind: Kind,
+        age: Option<i64>,
+        nickname: Option<String>,
+        vaccinated: bool,
+    }
+
+    fn checked_memory_handle() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE pet (id INTEGER PRIMARY KEY, name TEXT, kind TEXT, age INTEGER, vaccinated INTEGER);
+             INSERT INTO pet VALUES (1, 'Felix', 'Cat', 3, 1);
+             INSERT INTO pet VALUES (2, 'Rex', 'Dog', NULL, 0);",
+        )?;
+        Ok(db)
+    }
+
+    #[test]
+    fn test_deserialize_struct() -> Result<()> {
+        let db = checked_memory_handle()?;
+        let mut stmt = db.prepare("SELECT * FROM pet ORDER BY id")?;
+        let pets = stmt.query_as::<Pet, _>([])?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            vec![
+                Pet {
+                    id: Id(1),
+                    name: "Felix".to_owned(),
+                    kind: Kind::Cat,
+                    age: Some(3),
+                    nickname: None,
+                    vaccinated: true,
+                },
+                Pet {
+                    id: Id(2),
+                    name: "Rex".to_owned(),
+                    kind: Kind::Dog,
+                    age: None,
+                    nickname: None,
+                    vaccinated: false,
+                }
+            ],
+            pets
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_tuple_and_scalar() -> Result<()> {
+        let db = checked_memory_handle()?;
+        let pet: (i64, String, Vec<u8>) = db.query_row(
+            "SELECT id, name, x'0102' FROM pet WHERE id = 2",
+            [],
+            |row| row.deserialize(),
+        )?;
+        assert_eq!((2, "Rex".to_owned(), vec![1, 2]), pet);
+        let mut stmt = db.prepare("SELECT name FROM pet WHERE id = 1")?;
+        let mut rows = stmt.query([])?;
+        let name: &str = rows.next()?.unwrap().deserialize()?;
+        assert_eq!("Felix", name);
+
+        // field-less enums can also be stored as the index of the variant
+        let kind: Kind = db.query_row("SELECT 1", [], |row| row.deserialize())?;
+        assert_eq!(Kind::Dog, kind);
+        let id: Id = db.query_row("SELECT 42", [], |row| row.deserialize())?;
+        assert_eq!(Id(42), id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_errors() -> Result<()> {
+        let db = checked_memory_handle()?;
+        let err = db
+            .query_row("SELECT id, name FROM pet", [], |row| {
+                row.deserialize::<Pet>()
+            })
+            .unwrap_err();
+        assert_eq!(Error::InvalidColumnName("kind".to_owned()), err);
+
+        let err = db
+            .query_row("SELECT name AS id FROM pet", [], |row| {
+                #[derive(Debug, Deserialize)]
+                struct Row {
+                    #[allow(dead_code)]
+                    id: i64,
+                }
+                row.deserialize::<Row>()
+            })
+            .unwrap_err();
+        assert_eq!(
+            Error::InvalidColumnType(0, "id".to_owned(), Type::Text),
+            err
+        );
+
+        let err = db
+            .query_row("SELECT 'Bird' AS kind", [], |row| row.deserialize::<Kind>())
+            .unwrap_err();
+        match err {
+            Error::FromSqlConversionFailure(0, Type::Text, err) => {
+                assert!(err
+                    .to_string()
+                    .starts_with("column `kind`: unknown variant"));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let err = db
+            .query_row("SELECT 300", [], |row| row.deserialize::<u8>())
+            .unwrap_err();
+        assert_eq!(Error::IntegralValueOutOfRange(0, 300), err);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_deserialize_json() -> Result<()> {
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Shape {
+            Circle(f64),
+            Rect { w: i64, h: i64 },
+        }
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Drawing {
+            tags: Vec<String>,
+            shape: Shape,
+        }
+        let db = Connection::open_in_memory()?;
+        let drawing: Drawing = db.query_row(
+            r#"SELECT '["a", "b"]' AS tags, '{"Rect": {"w": 2, "h": 3}}' AS shape"#,
+            [],
+            |row| row.deserialize(),
+        )?;
+        assert_eq!(
+            Drawing {
+                tags: vec!["a".to_owned(), "b".to_owned()],
+                shape: Shape::Rect { w: 2, h: 3 },
+            },
+            drawing
+        );
+        Ok(())
+    }
+}
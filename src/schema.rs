@@ -0,0 +1,507 @@
+//! Typed schema introspection
+//!
+//! Reads the schema table and the `table_info` / `table_xinfo`,
+//! `index_list`, `index_info` and `foreign_key_list` pragmas of a database.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result, MAIN_DB};
+//! fn print_tables(conn: &Connection) -> Result<()> {
+//!     conn.execute_batch("CREATE TABLE person(id INTEGER PRIMARY KEY, name TEXT NOT NULL);")?;
+//!     let schema = conn.schema(MAIN_DB)?;
+//!     for table in &schema.tables {
+//!         let columns: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+//!         println!("{}({})", table.name, columns.join(", "));
+//!     }
+//!     assert!(schema.table("person").unwrap().column("name").unwrap().not_null);
+//!     Ok(())
+//! }
+//! # fn main() { print_tables(&Connection::open_in_memory().unwrap()).unwrap(); }
+//! ```
+
+mod diff;
+
+pub use self::diff::{diff, SchemaDiff, TableDiff};
+
+use crate::pragma::Sql;
+use crate::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use crate::{Connection, DatabaseName, Result, Row};
+
+/// Schema of a database, see [`Connection::schema`].
+///
+/// Internal objects (whose name starts with `sqlite_`) are not included.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Schema {
+    /// Tables, ordered by name
+    pub tables: Vec<Table>,
+    /// Views, ordered by name
+    pub views: Vec<View>,
+    /// Triggers, ordered by name
+    pub triggers: Vec<Trigger>,
+}
+
+impl Schema {
+    /// Find a table by name.
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Find a view by name.
+    pub fn view(&self, name: &str) -> Option<&View> {
+        self.views
+            .iter()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Find a trigger by name.
+    pub fn trigger(&self, name: &str) -> Option<&Trigger> {
+        self.triggers
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A table (including virtual tables).
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Table {
+    /// Name
+    pub name: String,
+    /// `CREATE TABLE` statement
+    pub sql: String,
+    /// Columns, in declaration order
+    pub columns: Vec<Column>,
+    /// Indexes, including the ones created for `UNIQUE` and `PRIMARY KEY`
+    /// constraints
+    pub indexes: Vec<Index>,
+    /// Foreign keys
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+impl Table {
+    /// Find a column by name.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Find an index by name.
+    pub fn index(&self, name: &str) -> Option<&Index> {
+        self.indexes
+            .iter()
+            .find(|i| i.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A column of a table or a view.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Column {
+    /// Name
+    pub name: String,
+    /// Declared type (`None` if the column has no type)
+    pub decl_type: Option<String>,
+    /// `true` if the column has a `NOT NULL` constraint
+    pub not_null: bool,
+    /// SQL text of the default value
+    pub default: Option<String>,
+    /// 1-based position of the column in the primary key, 0 if the column is
+    /// not part of the primary key
+    pub primary_key: usize,
+    /// Hidden or generated column
+    pub kind: ColumnKind,
+}
+
+/// Kind of a [`Column`], as reported by `PRAGMA table_xinfo`.
+///
+/// SQLite versions older than 3.26.0 only report [`ColumnKind::Normal`]
+/// columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ColumnKind {
+    /// Ordinary column
+    Normal,
+    /// Hidden column of a virtual table
+    Hidden,
+    /// `GENERATED ALWAYS AS (..) VIRTUAL` column
+    Virtual,
+    /// `GENERATED ALWAYS AS (..) STORED` column
+    Stored,
+}
+
+impl FromSql for ColumnKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(ColumnKind::Normal),
+            1 => Ok(ColumnKind::Hidden),
+            2 => Ok(ColumnKind::Virtual),
+            3 => Ok(ColumnKind::Stored),
+            n => Err(FromSqlError::OutOfRange(n)),
+        }
+    }
+}
+
+/// An index of a table.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Index {
+    /// Name
+    pub name: String,
+    /// `true` if the index is `UNIQUE`
+    pub unique: bool,
+    /// How the index was created
+    pub origin: IndexOrigin,
+    /// `true` if the index has a `WHERE` clause
+    pub partial: bool,
+    /// Indexed columns, `None` for an expression
+    pub columns: Vec<Option<String>>,
+    /// `CREATE INDEX` statement (`None` for the indexes created by SQLite)
+    pub sql: Option<String>,
+}
+
+/// How an [`Index`] was created.
+///
+/// SQLite versions older than 3.8.9 only report [`IndexOrigin::CreateIndex`]
+/// indexes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IndexOrigin {
+    /// `CREATE INDEX` statement
+    CreateIndex,
+    /// `UNIQUE` constraint
+    Unique,
+    /// `PRIMARY KEY` constraint
+    PrimaryKey,
+}
+
+impl FromSql for IndexOrigin {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "c" => Ok(IndexOrigin::CreateIndex),
+            "u" => Ok(IndexOrigin::Unique),
+            "pk" => Ok(IndexOrigin::PrimaryKey),
+            s => Err(FromSqlError::Other(
+                format!("invalid index origin: {}", s).into(),
+            )),
+        }
+    }
+}
+
+/// A foreign key of a table.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ForeignKey {
+    /// Referenced table
+    pub table: String,
+    /// Columns of the child table
+    pub from: Vec<String>,
+    /// Columns of the referenced table, `None` for its primary key
+    pub to: Vec<Option<String>>,
+    /// `ON UPDATE` action
+    pub on_update: ForeignKeyAction,
+    /// `ON DELETE` action
+    pub on_delete: ForeignKeyAction,
+}
+
+/// `ON UPDATE` / `ON DELETE` action of a [`ForeignKey`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ForeignKeyAction {
+    /// `NO ACTION`
+    NoAction,
+    /// `RESTRICT`
+    Restrict,
+    /// `SET NULL`
+    SetNull,
+    /// `SET DEFAULT`
+    SetDefault,
+    /// `CASCADE`
+    Cascade,
+}
+
+impl FromSql for ForeignKeyAction {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "NO ACTION" => Ok(ForeignKeyAction::NoAction),
+            "RESTRICT" => Ok(ForeignKeyAction::Restrict),
+            "SET NULL" => Ok(ForeignKeyAction::SetNull),
+            "SET DEFAULT" => Ok(ForeignKeyAction::SetDefault),
+            "CASCADE" => Ok(ForeignKeyAction::Cascade),
+            s => Err(FromSqlError::Other(
+                format!("invalid foreign key action: {}", s).into(),
+            )),
+        }
+    }
+}
+
+/// A view.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct View {
+    /// Name
+    pub name: String,
+    /// `CREATE VIEW` statement
+    pub sql: String,
+    /// Columns
+    pub columns: Vec<Column>,
+}
+
+/// A trigger.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Trigger {
+    /// Name
+    pub name: String,
+    /// Table or view the trigger is attached to
+    pub table: String,
+    /// `CREATE TRIGGER` statement
+    pub sql: String,
+}
+
+struct Object {
+    kind: String,
+    name: String,
+    table: String,
+    sql: Option<String>,
+}
+
+impl Connection {
+    /// Read the schema of the database `db_name`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `db_name` is not attached or if the schema cannot
+    /// be read.
+    pub fn schema(&self, db_name: DatabaseName<'_>) -> Result<Schema> {
+        let mut schema_name = Sql::new();
+        schema_name.push_schema_name(db_name);
+        // `sqlite_schema` is only recognized since SQLite 3.33.0, its legacy
+        // name works with all versions
+        let sql = format!(
+            "SELECT type, name, tbl_name, sql FROM {}.sqlite_master \
+             WHERE name NOT LIKE 'sqlite\\_%' ESCAPE '\\' ORDER BY name",
+            schema_name.as_str()
+        );
+        let mut stmt = self.prepare(&sql)?;
+        let objects = stmt
+            .query_map([], |row| {
+                Ok(Object {
+                    kind: row.get(0)?,
+                    name: row.get(1)?,
+                    table: row.get(2)?,
+                    sql: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut schema = Schema {
+            tables: Vec::new(),
+            views: Vec::new(),
+            triggers: Vec::new(),
+        };
+        for object in &objects {
+            match object.kind.as_str() {
+                "table" => schema.tables.push(Table {
+                    columns: self.columns_of(db_name, &object.name)?,
+                    indexes: self.indexes_of(db_name, &object.name, &objects)?,
+                    foreign_keys: self.foreign_keys_of(db_name, &object.name)?,
+                    name: object.name.clone(),
+                    sql: object.sql.clone().unwrap_or_default(),
+                }),
+                "view" => schema.views.push(View {
+                    columns: self.columns_of(db_name, &object.name)?,
+                    name: object.name.clone(),
+                    sql: object.sql.clone().unwrap_or_default(),
+                }),
+                "trigger" => schema.triggers.push(Trigger {
+                    name: object.name.clone(),
+                    table: object.table.clone(),
+                    sql: object.sql.clone().unwrap_or_default(),
+                }),
+                _ => {}
+            }
+        }
+        Ok(schema)
+    }
+
+    fn columns_of(&self, db_name: DatabaseName<'_>, table: &str) -> Result<Vec<Column>> {
+        let xinfo = crate::version_number() >= 3_026_000;
+        let pragma_name = if xinfo { "table_xinfo" } else { "table_info" };
+        let mut columns = Vec::new();
+        self.pragma(Some(db_name), pragma_name, &table, |row| {
+            let decl_type: String = row.get("type")?;
+            columns.push(Column {
+                name: row.get("name")?,
+                decl_type: Some(decl_type).filter(|t| !t.is_empty()),
+                not_null: row.get("notnull")?,
+                default: row.get("dflt_value")?,
+                primary_key: row.get("pk")?,
+                kind: if xinfo {
+                    row.get("hidden")?
+                } else {
+                    ColumnKind::Normal
+                },
+            });
+            Ok(())
+        })?;
+        Ok(columns)
+    }
+
+    fn indexes_of(
+        &self,
+        db_name: DatabaseName<'_>,
+        table: &str,
+        objects: &[Object],
+    ) -> Result<Vec<Index>> {
+        let mut indexes = Vec::new();
+        self.pragma(Some(db_name), "index_list", &table, |row| {
+            // `origin` and `partial` are reported since SQLite 3.8.9
+            let extended = row.as_ref().column_count() > 3;
+            let name: String = row.get("name")?;
+            let sql = objects
+                .iter()
+                .find(|o| o.kind == "index" && o.name == name)
+                .and_then(|o| o.sql.clone());
+            indexes.push(Index {
+                unique: row.get("unique")?,
+                origin: if extended {
+                    row.get("origin")?
+                } else {
+                    IndexOrigin::CreateIndex
+                },
+                partial: extended && row.get("partial")?,
+                columns: Vec::new(),
+                sql,
+                name,
+            });
+            Ok(())
+        })?;
+        for index in &mut indexes {
+            let columns = &mut index.columns;
+            self.pragma(Some(db_name), "index_info", &index.name, |row| {
+                columns.push(row.get("name")?);
+                Ok(())
+            })?;
+        }
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(indexes)
+    }
+
+    fn foreign_keys_of(&self, db_name: DatabaseName<'_>, table: &str) -> Result<Vec<ForeignKey>> {
+        let mut foreign_keys: Vec<(i64, ForeignKey)> = Vec::new();
+        self.pragma(Some(db_name), "foreign_key_list", &table, |row| {
+            let id: i64 = row.get("id")?;
+            let (from, to) = (row.get("from")?, row.get("to")?);
+            match foreign_keys.last_mut() {
+                Some((last, fk)) if *last == id => {
+                    fk.from.push(from);
+                    fk.to.push(to);
+                }
+                _ => foreign_keys.push((id, foreign_key(row, from, to)?)),
+            }
+            Ok(())
+        })?;
+        // the pragma lists the foreign keys in reverse order
+        foreign_keys.sort_by_key(|(id, _)| *id);
+        Ok(foreign_keys.into_iter().map(|(_, fk)| fk).collect())
+    }
+}
+
+fn foreign_key(row: &Row<'_>, from: String, to: Option<String>) -> Result<ForeignKey> {
+    Ok(ForeignKey {
+        table: row.get("table")?,
+        from: vec![from],
+        to: vec![to],
+        on_update: row.get("on_update")?,
+        on_delete: row.get("on_delete")?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ColumnKind, ForeignKeyAction, IndexOrigin};
+    use crate::{Connection, DatabaseName, Result, MAIN_DB};
+
+    #[test]
+    fn test_schema() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE team(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE);
+             CREATE TABLE person(
+                 id INTEGER PRIMARY KEY,
+                 name TEXT NOT NULL DEFAULT 'x',
+                 team_id INTEGER REFERENCES team ON DELETE CASCADE,
+                 note,
+                 FOREIGN KEY (name, team_id) REFERENCES member(name, team) ON UPDATE SET NULL
+             );
+             CREATE INDEX person_name ON person(name, lower(note)) WHERE note IS NOT NULL;
+             CREATE VIEW names AS SELECT name FROM person;
+             CREATE TRIGGER person_delete AFTER DELETE ON person BEGIN SELECT 1; END;",
+        )?;
+        let schema = db.schema(MAIN_DB)?;
+        let names: Vec<&str> = schema.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(vec!["person", "team"], names);
+
+        let person = schema.table("person").unwrap();
+        assert!(person.sql.starts_with("CREATE TABLE person"));
+        assert_eq!(4, person.columns.len());
+        let id = person.column("id").unwrap();
+        assert_eq!(Some("INTEGER"), id.decl_type.as_deref());
+        assert_eq!(1, id.primary_key);
+        let name = person.column("name").unwrap();
+        assert!(name.not_null);
+        assert_eq!(Some("'x'"), name.default.as_deref());
+        assert_eq!(ColumnKind::Normal, name.kind);
+        assert_eq!(None, person.column("note").unwrap().decl_type);
+
+        let index = person.index("person_name").unwrap();
+        assert!(!index.unique);
+        assert_eq!(IndexOrigin::CreateIndex, index.origin);
+        assert!(index.partial);
+        assert_eq!(vec![Some("name".to_owned()), None], index.columns);
+        assert!(index.sql.is_some());
+
+        let fks = &person.foreign_keys;
+        assert_eq!(2, fks.len());
+        assert_eq!("team", fks[1].table);
+        assert_eq!(vec!["team_id"], fks[1].from);
+        assert_eq!(vec![None], fks[1].to);
+        assert_eq!(ForeignKeyAction::Cascade, fks[1].on_delete);
+        assert_eq!(ForeignKeyAction::NoAction, fks[1].on_update);
+        assert_eq!("member", fks[0].table);
+        assert_eq!(vec!["name", "team_id"], fks[0].from);
+        assert_eq!(ForeignKeyAction::SetNull, fks[0].on_update);
+
+        let team = schema.table("team").unwrap();
+        assert_eq!(1, team.indexes.len());
+        assert!(team.indexes[0].unique);
+        assert_eq!(IndexOrigin::Unique, team.indexes[0].origin);
+        assert_eq!(None, team.indexes[0].sql);
+
+        let view = schema.view("names").unwrap();
+        assert_eq!(
+            vec!["name"],
+            view.columns.iter().map(|c| &c.name).collect::<Vec<_>>()
+        );
+        assert_eq!("person", schema.trigger("person_delete").unwrap().table);
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_attached() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "ATTACH DATABASE ':memory:' AS \"other db\";
+             CREATE TABLE \"other db\".foo(x, y AS (x + 1) STORED);",
+        )?;
+        assert!(db.schema(MAIN_DB)?.tables.is_empty());
+        let schema = db.schema(DatabaseName::Attached("other db"))?;
+        let foo = schema.table("foo").unwrap();
+        assert_eq!(ColumnKind::Stored, foo.column("y").unwrap().kind);
+        assert!(db.schema(DatabaseName::Attached("missing")).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,836 @@
+//! Differences between two schemas and the SQL migrating one to the other
+
+use std::fmt;
+
+use super::{Column, ColumnKind, Index, Schema, Table, Trigger, View};
+use crate::migrations::check_foreign_keys;
+use crate::{Connection, Result};
+
+/// Differences between two schemas, see [`diff`].
+///
+/// Objects are matched by name (ignoring ASCII case) and compared by their
+/// SQL text, ignoring case and whitespace outside of string literals.
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct SchemaDiff<'a> {
+    /// Tables only in the target schema
+    pub added_tables: Vec<&'a Table>,
+    /// Tables only in the source schema
+    pub removed_tables: Vec<&'a Table>,
+    /// Tables whose definition or indexes differ
+    pub changed_tables: Vec<TableDiff<'a>>,
+    /// Views only in the target schema
+    pub added_views: Vec<&'a View>,
+    /// Views only in the source schema
+    pub removed_views: Vec<&'a View>,
+    /// Views whose definition differs, source first
+    pub changed_views: Vec<(&'a View, &'a View)>,
+    /// Triggers only in the target schema
+    pub added_triggers: Vec<&'a Trigger>,
+    /// Triggers only in the source schema
+    pub removed_triggers: Vec<&'a Trigger>,
+    /// Triggers whose definition differs, source first
+    pub changed_triggers: Vec<(&'a Trigger, &'a Trigger)>,
+    from: &'a Schema,
+    to: &'a Schema,
+}
+
+/// Differences between two versions of a table.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct TableDiff<'a> {
+    /// Table in the source schema
+    pub from: &'a Table,
+    /// Table in the target schema
+    pub to: &'a Table,
+    /// Columns only in the target table
+    pub added_columns: Vec<&'a Column>,
+    /// Columns only in the source table
+    pub removed_columns: Vec<&'a Column>,
+    /// Columns whose definition differs, source first
+    pub changed_columns: Vec<(&'a Column, &'a Column)>,
+    /// Indexes only in the target table
+    pub added_indexes: Vec<&'a Index>,
+    /// Indexes only in the source table
+    pub removed_indexes: Vec<&'a Index>,
+    /// Indexes whose definition differs, source first
+    pub changed_indexes: Vec<(&'a Index, &'a Index)>,
+    /// `true` if the table must be rebuilt because its definition changed in
+    /// a way `ALTER TABLE .. ADD COLUMN` cannot do
+    pub rebuild: bool,
+}
+
+/// Compare the schemas `from` and `to`, read from two connections or from two
+/// databases of the same connection.
+///
+/// Only the indexes created by `CREATE INDEX` are compared, the other ones
+/// being part of the table definition.
+///
+/// ```rust
+/// # use rusqlite::{Connection, Result, MAIN_DB};
+/// use rusqlite::schema;
+///
+/// fn check_migrations(migrated: &Connection, expected: &Connection) -> Result<()> {
+///     let (from, to) = (migrated.schema(MAIN_DB)?, expected.schema(MAIN_DB)?);
+///     let diff = schema::diff(&from, &to);
+///     assert!(diff.is_empty(), "missing migration:\n{}", diff.to_sql());
+///     Ok(())
+/// }
+/// # fn main() {
+/// #     let db = Connection::open_in_memory().unwrap();
+/// #     check_migrations(&db, &Connection::open_in_memory().unwrap()).unwrap();
+/// # }
+/// ```
+pub fn diff<'a>(from: &'a Schema, to: &'a Schema) -> SchemaDiff<'a> {
+    let (added_tables, removed_tables, common) =
+        match_by_name(&from.tables, &to.tables, |t| &t.name);
+    let changed_tables = common
+        .into_iter()
+        .filter_map(|(from, to)| table_diff(from, to))
+        .collect();
+    let (added_views, removed_views, common) = match_by_name(&from.views, &to.views, |v| &v.name);
+    let changed_views = common
+        .into_iter()
+        .filter(|(from, to)| normalize(&from.sql) != normalize(&to.sql))
+        .collect();
+    let (added_triggers, removed_triggers, common) =
+        match_by_name(&from.triggers, &to.triggers, |t| &t.name);
+    let changed_triggers = common
+        .into_iter()
+        .filter(|(from, to)| normalize(&from.sql) != normalize(&to.sql))
+        .collect();
+    SchemaDiff {
+        added_tables,
+        removed_tables,
+        changed_tables,
+        added_views,
+        removed_views,
+        changed_views,
+        added_triggers,
+        removed_triggers,
+        changed_triggers,
+        from,
+        to,
+    }
+}
+
+impl SchemaDiff<'_> {
+    /// `true` if the schemas are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added_tables.is_empty()
+            && self.removed_tables.is_empty()
+            && self.changed_tables.is_empty()
+            && self.added_views.is_empty()
+            && self.removed_views.is_empty()
+            && self.changed_views.is_empty()
+            && self.added_triggers.is_empty()
+            && self.removed_triggers.is_empty()
+            && self.changed_triggers.is_empty()
+    }
+
+    /// SQL statements bringing the source schema to the target one, in the
+    /// `main` database.
+    ///
+    /// Tables are changed with `ALTER TABLE .. ADD COLUMN` when possible, and
+    /// otherwise rebuilt following the
+    /// [procedure](https://sqlite.org/lang_altertable.html#otheralter)
+    /// documented by SQLite: the columns present in both versions are copied,
+    /// the other ones get their default value. When a table is rebuilt, all
+    /// the views and triggers are recreated, as they may reference it.
+    ///
+    /// The statements must be run in a transaction, with foreign keys
+    /// disabled, as [`SchemaDiff::apply`] does.
+    pub fn to_sql(&self) -> String {
+        let rebuild = self.changed_tables.iter().any(|t| t.rebuild);
+        let mut sql = String::new();
+
+        if rebuild {
+            for trigger in &self.from.triggers {
+                push_drop(&mut sql, "TRIGGER", &trigger.name);
+            }
+            for view in &self.from.views {
+                push_drop(&mut sql, "VIEW", &view.name);
+            }
+        } else {
+            let triggers = self.changed_triggers.iter().map(|t| t.0);
+            for trigger in self.removed_triggers.iter().copied().chain(triggers) {
+                push_drop(&mut sql, "TRIGGER", &trigger.name);
+            }
+            let views = self.changed_views.iter().map(|v| v.0);
+            for view in self.removed_views.iter().copied().chain(views) {
+                push_drop(&mut sql, "VIEW", &view.name);
+            }
+        }
+
+        for table in self.changed_tables.iter().filter(|t| !t.rebuild) {
+            let indexes = table.changed_indexes.iter().map(|i| i.0);
+            for index in table.removed_indexes.iter().copied().chain(indexes) {
+                push_drop(&mut sql, "INDEX", &index.name);
+            }
+        }
+        for table in &self.removed_tables {
+            push_drop(&mut sql, "TABLE", &table.name);
+        }
+        for table in &self.added_tables {
+            push_statement(&mut sql, &table.sql);
+            push_create_indexes(&mut sql, &table.indexes);
+        }
+        for table in &self.changed_tables {
+            if table.rebuild {
+                table.push_rebuild(&mut sql, self.from, self.to);
+            } else {
+                table.push_add_columns(&mut sql);
+                let indexes = table.changed_indexes.iter().map(|i| i.1);
+                push_create_indexes(&mut sql, table.added_indexes.iter().copied().chain(indexes));
+            }
+        }
+
+        if rebuild {
+            for view in &self.to.views {
+                push_statement(&mut sql, &view.sql);
+            }
+            for trigger in &self.to.triggers {
+                push_statement(&mut sql, &trigger.sql);
+            }
+        } else {
+            let views = self.changed_views.iter().map(|v| v.1);
+            for view in self.added_views.iter().copied().chain(views) {
+                push_statement(&mut sql, &view.sql);
+            }
+            let triggers = self.changed_triggers.iter().map(|t| t.1);
+            for trigger in self.added_triggers.iter().copied().chain(triggers) {
+                push_statement(&mut sql, &trigger.sql);
+            }
+        }
+        sql
+    }
+
+    /// Run the statements returned by [`SchemaDiff::to_sql`] on the `main`
+    /// database of `conn`, in a transaction and with foreign keys disabled.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if a statement fails or if `PRAGMA foreign_key_check`
+    /// reports a violation, in which case the transaction is rolled back.
+    pub fn apply(&self, conn: &mut Connection) -> Result<()> {
+        // `foreign_keys` cannot be changed in a transaction
+        let foreign_keys: bool = conn.pragma_query_value(None, "foreign_keys", |row| row.get(0))?;
+        if foreign_keys {
+            conn.pragma_update(None, "foreign_keys", &false)?;
+        }
+        let result = self.apply_in_transaction(conn);
+        if foreign_keys {
+            // restore it even on failure, but report the original error first
+            let restored = conn.pragma_update(None, "foreign_keys", &true);
+            result?;
+            return restored;
+        }
+        result
+    }
+
+    fn apply_in_transaction(&self, conn: &mut Connection) -> Result<()> {
+        let tx = conn.transaction()?;
+        tx.execute_batch(&self.to_sql())?;
+        check_foreign_keys(&tx, "after applying the schema diff")?;
+        tx.commit()
+    }
+}
+
+impl fmt::Debug for SchemaDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaDiff")
+            .field("added_tables", &self.added_tables)
+            .field("removed_tables", &self.removed_tables)
+            .field("changed_tables", &self.changed_tables)
+            .field("added_views", &self.added_views)
+            .field("removed_views", &self.removed_views)
+            .field("changed_views", &self.changed_views)
+            .field("added_triggers", &self.added_triggers)
+            .field("removed_triggers", &self.removed_triggers)
+            .field("changed_triggers", &self.changed_triggers)
+            .finish()
+    }
+}
+
+impl TableDiff<'_> {
+    fn push_add_columns(&self, sql: &mut String) {
+        let to = match TableDef::parse(&self.to.sql) {
+            Some(def) => def,
+            None => return,
+        };
+        for column in &self.added_columns {
+            if let Some(def) = to.column(&column.name) {
+                push_statement(
+                    sql,
+                    &format!("ALTER TABLE {} ADD COLUMN {}", quote(&self.to.name), def),
+                );
+            }
+        }
+    }
+
+    fn push_rebuild(&self, sql: &mut String, from: &Schema, to: &Schema) {
+        let name = &self.to.name;
+        let body = match TableDef::parse(&self.to.sql) {
+            Some(def) => def.body,
+            None => {
+                // virtual table
+                push_drop(sql, "TABLE", name);
+                push_statement(sql, &self.to.sql);
+                return;
+            }
+        };
+        // tables, indexes, views and triggers share the same namespace
+        let mut new_name = format!("new_{}", name);
+        while [from, to]
+            .iter()
+            .any(|schema| has_object(schema, &new_name))
+        {
+            new_name.push('_');
+        }
+        push_statement(sql, &format!("CREATE TABLE {}{}", quote(&new_name), body));
+        let columns: Vec<String> = self
+            .to
+            .columns
+            .iter()
+            .filter(|c| c.kind == ColumnKind::Normal)
+            .filter(
+                |c| matches!(self.from.column(&c.name), Some(f) if f.kind == ColumnKind::Normal),
+            )
+            .map(|c| quote(&c.name))
+            .collect();
+        if !columns.is_empty() {
+            let columns = columns.join(", ");
+            push_statement(
+                sql,
+                &format!(
+                    "INSERT INTO {} ({}) SELECT {} FROM {}",
+                    quote(&new_name),
+                    columns,
+                    columns,
+                    quote(name)
+                ),
+            );
+        }
+        push_drop(sql, "TABLE", name);
+        push_statement(
+            sql,
+            &format!("ALTER TABLE {} RENAME TO {}", quote(&new_name), quote(name)),
+        );
+        push_create_indexes(sql, &self.to.indexes);
+    }
+}
+
+fn table_diff<'a>(from: &'a Table, to: &'a Table) -> Option<TableDiff<'a>> {
+    let from_def = TableDef::parse(&from.sql);
+    let to_def = TableDef::parse(&to.sql);
+    let (added_columns, removed_columns, common) =
+        match_by_name(&from.columns, &to.columns, |c| &c.name);
+    let changed_columns: Vec<_> = common
+        .into_iter()
+        .filter(|(f, t)| {
+            f != t
+                || match (&from_def, &to_def) {
+                    (Some(from_def), Some(to_def)) => {
+                        from_def.column(&f.name).map(normalize)
+                            != to_def.column(&t.name).map(normalize)
+                    }
+                    _ => false,
+                }
+        })
+        .collect();
+
+    let from_indexes: Vec<&Index> = from.indexes.iter().filter(|i| i.sql.is_some()).collect();
+    let to_indexes: Vec<&Index> = to.indexes.iter().filter(|i| i.sql.is_some()).collect();
+    let (added_indexes, removed_indexes, common) =
+        match_by_name(from_indexes, to_indexes, |i| &i.name);
+    let changed_indexes: Vec<_> = common
+        .into_iter()
+        .filter(|(f, t)| f.sql.as_deref().map(normalize) != t.sql.as_deref().map(normalize))
+        .collect();
+
+    let same_definition = match (&from_def, &to_def) {
+        (Some(from_def), Some(to_def)) => from_def.normalized() == to_def.normalized(),
+        _ => normalize(&from.sql) == normalize(&to.sql),
+    };
+    if same_definition
+        && added_indexes.is_empty()
+        && removed_indexes.is_empty()
+        && changed_indexes.is_empty()
+    {
+        return None;
+    }
+    let rebuild = !same_definition
+        && !match (&from_def, &to_def) {
+            (Some(from_def), Some(to_def)) => {
+                removed_columns.is_empty()
+                    && changed_columns.is_empty()
+                    && can_add_columns(from_def, to_def, &added_columns)
+            }
+            _ => false,
+        };
+    Some(TableDiff {
+        from,
+        to,
+        added_columns,
+        removed_columns,
+        changed_columns,
+        added_indexes,
+        removed_indexes,
+        changed_indexes,
+        rebuild,
+    })
+}
+
+// See the restrictions of https://sqlite.org/lang_altertable.html#altertabaddcol
+fn can_add_columns(from: &TableDef<'_>, to: &TableDef<'_>, added: &[&Column]) -> bool {
+    let same_start = from.columns.len() + added.len() == to.columns.len()
+        && from
+            .columns
+            .iter()
+            .zip(&to.columns)
+            .all(|(f, t)| f.0 == t.0 && normalize(f.1) == normalize(t.1));
+    let same_constraints = from
+        .constraints
+        .iter()
+        .map(|c| normalize(c))
+        .eq(to.constraints.iter().map(|c| normalize(c)))
+        && normalize(from.options) == normalize(to.options);
+    same_start
+        && same_constraints
+        && added.iter().all(|column| {
+            let def = match to.column(&column.name) {
+                Some(def) => def,
+                None => return false,
+            };
+            let default = column
+                .default
+                .as_deref()
+                .filter(|d| !d.eq_ignore_ascii_case("NULL"));
+            let constant_default = match default {
+                Some(d) => {
+                    !d.starts_with('(')
+                        && !["CURRENT_TIME", "CURRENT_DATE", "CURRENT_TIMESTAMP"]
+                            .iter()
+                            .any(|c| d.eq_ignore_ascii_case(c))
+                }
+                None => true,
+            };
+            column.primary_key == 0
+                && column.kind != ColumnKind::Stored
+                && !has_keyword(def, "UNIQUE")
+                && !(column.not_null && default.is_none())
+                && !(has_keyword(def, "REFERENCES") && default.is_some())
+                && constant_default
+        })
+}
+
+fn match_by_name<'a, T, I, F>(
+    from: I,
+    to: I,
+    name: F,
+) -> (Vec<&'a T>, Vec<&'a T>, Vec<(&'a T, &'a T)>)
+where
+    I: IntoIterator<Item = &'a T>,
+    F: Fn(&T) -> &String,
+{
+    let from: Vec<&T> = from.into_iter().collect();
+    let to: Vec<&T> = to.into_iter().collect();
+    let find = |objects: &[&'a T], n: &str| {
+        objects
+            .iter()
+            .copied()
+            .find(|o| name(o).eq_ignore_ascii_case(n))
+    };
+    let added = to
+        .iter()
+        .copied()
+        .filter(|t| find(&from, name(t)).is_none())
+        .collect();
+    let removed = from
+        .iter()
+        .copied()
+        .filter(|f| find(&to, name(f)).is_none())
+        .collect();
+    let common = from
+        .iter()
+        .filter_map(|f| find(&to, name(f)).map(|t| (*f, t)))
+        .collect();
+    (added, removed, common)
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn push_statement(sql: &mut String, statement: &str) {
+    sql.push_str(statement);
+    sql.push_str(";\n");
+}
+
+fn has_object(schema: &Schema, name: &str) -> bool {
+    schema.table(name).is_some()
+        || schema.view(name).is_some()
+        || schema.trigger(name).is_some()
+        || schema.tables.iter().any(|t| t.index(name).is_some())
+}
+
+fn push_drop(sql: &mut String, kind: &str, name: &str) {
+    push_statement(sql, &format!("DROP {} {}", kind, quote(name)));
+}
+
+fn push_create_indexes<'a, I: IntoIterator<Item = &'a Index>>(sql: &mut String, indexes: I) {
+    for index in indexes {
+        if let Some(ref index_sql) = index.sql {
+            push_statement(sql, index_sql);
+        }
+    }
+}
+
+/// Definitions of a `CREATE TABLE` statement
+struct TableDef<'a> {
+    /// From the opening parenthesis to the end
+    body: &'a str,
+    /// Lowercase name and definition of each column
+    columns: Vec<(String, &'a str)>,
+    constraints: Vec<&'a str>,
+    /// `WITHOUT ROWID` and `STRICT`
+    options: &'a str,
+}
+
+impl<'a> TableDef<'a> {
+    fn parse(sql: &'a str) -> Option<TableDef<'a>> {
+        match words(sql).nth(1) {
+            Some(w) if !w.eq_ignore_ascii_case("VIRTUAL") => {}
+            _ => return None,
+        }
+        let b = sql.as_bytes();
+        let (mut depth, mut open, mut start) = (0, 0, 0);
+        let mut defs = Vec::new();
+        let mut i = 0;
+        while i < b.len() {
+            match b[i] {
+                b'(' => {
+                    depth += 1;
+                    if depth == 1 {
+                        open = i;
+                        start = i + 1;
+                    }
+                }
+                b')' if depth == 1 => {
+                    defs.push(sql[start..i].trim());
+                    let (mut columns, mut constraints) = (Vec::new(), Vec::new());
+                    for def in defs {
+                        // table constraints start with a keyword, not a
+                        // quoted identifier
+                        let first = def.split(|c: char| !c.is_ascii_alphabetic()).next()?;
+                        if ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+                            .iter()
+                            .any(|k| first.eq_ignore_ascii_case(k))
+                        {
+                            constraints.push(def);
+                        } else {
+                            columns.push((column_name(def)?, def));
+                        }
+                    }
+                    return Some(TableDef {
+                        body: &sql[open..],
+                        columns,
+                        constraints,
+                        options: &sql[i + 1..],
+                    });
+                }
+                b')' => depth -= 1,
+                b',' if depth == 1 => {
+                    defs.push(sql[start..i].trim());
+                    start = i + 1;
+                }
+                _ => {}
+            }
+            i = skip_token(b, i)? + 1;
+        }
+        None
+    }
+
+    fn column(&self, name: &str) -> Option<&'a str> {
+        self.columns
+            .iter()
+            .find(|c| c.0.eq_ignore_ascii_case(name))
+            .map(|c| c.1)
+    }
+
+    fn normalized(&self) -> (Vec<String>, Vec<String>, String) {
+        (
+            self.columns.iter().map(|c| normalize(c.1)).collect(),
+            self.constraints.iter().map(|c| normalize(c)).collect(),
+            normalize(self.options),
+        )
+    }
+}
+
+/// Index of the last byte of the quoted identifier, string literal or comment
+/// starting at `i`, or `i`.
+fn skip_token(b: &[u8], i: usize) -> Option<usize> {
+    let find = |from: usize, end: &[u8]| {
+        b[from..]
+            .windows(end.len())
+            .position(|w| w == end)
+            .map(|p| from + p + end.len() - 1)
+    };
+    match b[i] {
+        q @ b'\'' | q @ b'"' | q @ b'`' => {
+            let mut j = i + 1;
+            loop {
+                j = find(j, &[q])?;
+                // a doubled quote is escaped
+                if b.get(j + 1) == Some(&q) {
+                    j += 2;
+                } else {
+                    return Some(j);
+                }
+            }
+        }
+        b'[' => find(i + 1, b"]"),
+        b'-' if b.get(i + 1) == Some(&b'-') => Some(find(i + 2, b"\n").unwrap_or(b.len() - 1)),
+        b'/' if b.get(i + 1) == Some(&b'*') => Some(find(i + 2, b"*/").unwrap_or(b.len() - 1)),
+        _ => Some(i),
+    }
+}
+
+/// Unquoted words, outside of quoted identifiers, string literals and
+/// comments.
+fn words(sql: &str) -> impl Iterator<Item = &str> {
+    let b = sql.as_bytes();
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        while i < b.len() {
+            if b[i].is_ascii_alphanumeric() || b[i] == b'_' {
+                let start = i;
+                while i < b.len() && (b[i].is_ascii_alphanumeric() || b[i] == b'_') {
+                    i += 1;
+                }
+                return Some(&sql[start..i]);
+            }
+            i = skip_token(b, i).unwrap_or(b.len()) + 1;
+        }
+        None
+    })
+}
+
+fn has_keyword(sql: &str, keyword: &str) -> bool {
+    words(sql).any(|w| w.eq_ignore_ascii_case(keyword))
+}
+
+fn column_name(def: &str) -> Option<String> {
+    let b = def.as_bytes();
+    let name = match b.first()? {
+        b'"' | b'`' | b'\'' => {
+            let end = skip_token(b, 0)?;
+            let q = b[0] as char;
+            def[1..end].replace(&format!("{}{}", q, q), &q.to_string())
+        }
+        b'[' => def[1..skip_token(b, 0)?].to_owned(),
+        _ => def
+            .split(|c: char| c.is_whitespace() || c == '(')
+            .next()?
+            .to_owned(),
+    };
+    Some(name.to_ascii_lowercase())
+}
+
+/// Uppercase `sql` and collapse whitespace and comments, outside of string
+/// literals.
+fn normalize(sql: &str) -> String {
+    let b = sql.as_bytes();
+    let mut normalized = String::with_capacity(sql.len());
+    let mut space = false;
+    let mut i = 0;
+    while i < b.len() {
+        let end = match skip_token(b, i) {
+            Some(end) if end == i => i + sql[i..].chars().next().map_or(1, char::len_utf8) - 1,
+            Some(end) => end,
+            None => b.len() - 1,
+        };
+        let token = &sql[i..=end];
+        let comment = token.starts_with("--") || token.starts_with("/*");
+        if comment || b[i].is_ascii_whitespace() {
+            space = true;
+        } else {
+            let punctuation = |c: Option<char>| matches!(c, Some('(') | Some(')') | Some(','));
+            if space
+                && !normalized.is_empty()
+                && !punctuation(normalized.chars().last())
+                && !punctuation(token.chars().next())
+            {
+                normalized.push(' ');
+            }
+            space = false;
+            if token.starts_with('\'') {
+                normalized.push_str(token);
+            } else {
+                normalized.push_str(&token.to_ascii_uppercase());
+            }
+        }
+        i = end + 1;
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, normalize, TableDef};
+    use crate::{Connection, DatabaseName, Error, ErrorCode, Result, MAIN_DB};
+
+    fn migrate(from: &str, to: &str) -> Result<(Connection, String)> {
+        let mut db = Connection::open_in_memory()?;
+        db.execute_batch(from)?;
+        let target = Connection::open_in_memory()?;
+        target.execute_batch(to)?;
+        let (source_schema, target_schema) = (db.schema(MAIN_DB)?, target.schema(MAIN_DB)?);
+        let diff = diff(&source_schema, &target_schema);
+        let sql = diff.to_sql();
+        diff.apply(&mut db)?;
+        let schema = db.schema(MAIN_DB)?;
+        let after = super::diff(&schema, &target_schema);
+        assert!(after.is_empty(), "{:?}\n{}", after, sql);
+        Ok((db, sql))
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            "CREATE TABLE FOO(A INT,B TEXT DEFAULT 'a  B')",
+            normalize("create table foo ( a int, -- x\n b  text default 'a  B' )")
+        );
+        let def = TableDef::parse(
+            "CREATE TABLE \"a(\"([x y] INT, \"b\"\"c\" CHECK (x > 0), FOREIGN KEY (x) REFERENCES t) STRICT",
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["x y", "b\"c"],
+            def.columns.iter().map(|c| &c.0).collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["FOREIGN KEY (x) REFERENCES t"], def.constraints);
+        assert_eq!(" STRICT", def.options);
+        assert!(TableDef::parse("CREATE VIRTUAL TABLE t USING fts5(x)").is_none());
+    }
+
+    #[test]
+    fn test_add_column() -> Result<()> {
+        let (db, sql) = migrate(
+            "CREATE TABLE foo(id INTEGER PRIMARY KEY, x TEXT);
+             INSERT INTO foo VALUES (1, 'a');",
+            "CREATE TABLE foo(id INTEGER PRIMARY KEY, x TEXT, y INT NOT NULL DEFAULT 0);
+             CREATE INDEX foo_y ON foo(y);",
+        )?;
+        assert_eq!(
+            "ALTER TABLE \"foo\" ADD COLUMN y INT NOT NULL DEFAULT 0;\nCREATE INDEX foo_y ON foo(y);\n",
+            sql
+        );
+        let y: i64 = db.query_row("SELECT y FROM foo WHERE id = 1", [], |r| r.get(0))?;
+        assert_eq!(0, y);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebuild() -> Result<()> {
+        let (db, sql) = migrate(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE team(id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE person(id INTEGER PRIMARY KEY, name TEXT, age TEXT, team_id REFERENCES team);
+             CREATE INDEX person_name ON person(name);
+             CREATE VIEW names AS SELECT name FROM person;
+             CREATE TRIGGER team_delete AFTER DELETE ON team BEGIN DELETE FROM person WHERE team_id = old.id; END;
+             INSERT INTO team VALUES (1, 'a');
+             INSERT INTO person VALUES (1, 'Ada', '36', 1);",
+            "CREATE TABLE team(id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE person(id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INTEGER, team_id REFERENCES team);
+             CREATE INDEX person_name ON person(name);
+             CREATE VIEW names AS SELECT name, age FROM person;
+             CREATE TRIGGER team_delete AFTER DELETE ON team BEGIN DELETE FROM person WHERE team_id = old.id; END;",
+        )?;
+        assert!(sql.contains("ALTER TABLE \"new_person\" RENAME TO \"person\""));
+        let age: i64 = db.query_row("SELECT age FROM names", [], |r| r.get(0))?;
+        assert_eq!(36, age);
+        let foreign_keys: bool = db.pragma_query_value(None, "foreign_keys", |r| r.get(0))?;
+        assert!(foreign_keys);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebuild_name_collision() -> Result<()> {
+        let (db, sql) = migrate(
+            "CREATE TABLE foo(x TEXT);
+             CREATE TABLE new_foo(y);
+             CREATE INDEX new_foo_ ON new_foo(y);
+             INSERT INTO foo VALUES ('a');",
+            "CREATE TABLE foo(x TEXT NOT NULL);
+             CREATE TABLE new_foo(y);
+             CREATE INDEX new_foo_ ON new_foo(y);",
+        )?;
+        assert!(sql.contains("ALTER TABLE \"new_foo__\" RENAME TO \"foo\""));
+        let x: String = db.query_row("SELECT x FROM foo", [], |r| r.get(0))?;
+        assert_eq!("a", x);
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_foreign_key_violation() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE parent(id INTEGER PRIMARY KEY);
+             CREATE TABLE child(id INTEGER PRIMARY KEY, parent_id INT);
+             INSERT INTO child VALUES (1, 2);",
+        )?;
+        let target = Connection::open_in_memory()?;
+        target.execute_batch(
+            "CREATE TABLE parent(id INTEGER PRIMARY KEY);
+             CREATE TABLE child(id INTEGER PRIMARY KEY, parent_id INT REFERENCES parent);",
+        )?;
+        let (source_schema, target_schema) = (db.schema(MAIN_DB)?, target.schema(MAIN_DB)?);
+        match diff(&source_schema, &target_schema).apply(&mut db) {
+            Err(Error::SqliteFailure(err, Some(msg))) => {
+                assert_eq!(ErrorCode::ConstraintViolation, err.code);
+                assert!(msg.contains("table child"), "{}", msg);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        // rolled back, with foreign keys enabled again
+        assert_eq!(source_schema, db.schema(MAIN_DB)?);
+        let foreign_keys: bool = db.pragma_query_value(None, "foreign_keys", |r| r.get(0))?;
+        assert!(foreign_keys);
+        Ok(())
+    }
+
+    #[test]
+    fn test_objects() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "ATTACH DATABASE ':memory:' AS a;
+             ATTACH DATABASE ':memory:' AS b;
+             CREATE TABLE a.foo(x);
+             CREATE TABLE a.bar(x);
+             CREATE INDEX a.bar_x ON bar(x);
+             CREATE VIEW a.v AS SELECT x FROM foo;
+             CREATE TRIGGER a.t AFTER INSERT ON foo BEGIN SELECT 1; END;
+             CREATE TABLE b.foo(x, y, z, PRIMARY KEY (x));
+             CREATE TABLE b.baz(x);
+             CREATE VIEW b.v AS SELECT x, y FROM foo;
+             CREATE VIEW b.w AS SELECT x FROM baz;",
+        )?;
+        let a = db.schema(DatabaseName::Attached("a"))?;
+        let b = db.schema(DatabaseName::Attached("b"))?;
+        let diff = diff(&a, &b);
+        assert_eq!("baz", diff.added_tables[0].name);
+        assert_eq!("bar", diff.removed_tables[0].name);
+        let foo = &diff.changed_tables[0];
+        assert_eq!(2, foo.added_columns.len());
+        assert!(foo.rebuild);
+        assert_eq!("w", diff.added_views[0].name);
+        assert_eq!("v", diff.changed_views[0].1.name);
+        assert_eq!("t", diff.removed_triggers[0].name);
+        assert!(diff.changed_triggers.is_empty());
+        assert!(super::diff(&b, &b).is_empty());
+        Ok(())
+    }
+}
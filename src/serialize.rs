@@ -0,0 +1,277 @@
+//! Serialize a database to / deserialize a database from an in-memory image.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, DatabaseName, Result};
+//! # use rusqlite::serialize::OwnedData;
+//! fn copy_db(src: &Connection) -> Result<Connection> {
+//!     let data = src.serialize(DatabaseName::Main)?;
+//!     let mut dst = Connection::open_in_memory()?;
+//!     dst.deserialize(DatabaseName::Main, OwnedData::from(&data[..]), false)?;
+//!     Ok(dst)
+//! }
+//! ```
+
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
+use std::{fmt, slice};
+
+use crate::error::error_from_handle;
+use crate::ffi;
+use crate::{Connection, DatabaseName, Result};
+
+/// Serialized database, borrowed directly from SQLite's memory
+/// (`SQLITE_SERIALIZE_NOCOPY`), see [`Connection::serialize_no_copy`].
+///
+/// The connection is mutably borrowed while this view is alive, so that the
+/// database cannot be modified (and its memory reallocated) under it.
+pub struct SharedData<'conn> {
+    phantom: PhantomData<&'conn mut Connection>,
+    ptr: NonNull<u8>,
+    sz: usize,
+}
+
+impl Deref for SharedData<'_> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.sz) }
+    }
+}
+
+/// Serialized database, stored in a buffer allocated by `sqlite3_malloc`.
+///
+/// This is the kind of buffer that can be handed over to SQLite by
+/// [`Connection::deserialize`].
+pub struct OwnedData {
+    ptr: NonNull<u8>,
+    sz: usize,
+}
+
+impl OwnedData {
+    /// Take ownership of a buffer of `sz` bytes.
+    ///
+    /// # Safety
+    ///
+    /// Caller must be certain that `ptr` is allocated by `sqlite3_malloc` and
+    /// that it is at least `sz` bytes long.
+    #[inline]
+    pub unsafe fn from_raw_nonnull(ptr: NonNull<u8>, sz: usize) -> OwnedData {
+        OwnedData { ptr, sz }
+    }
+
+    fn into_raw(self) -> (*mut u8, usize) {
+        let raw = (self.ptr.as_ptr(), self.sz);
+        std::mem::forget(self);
+        raw
+    }
+}
+
+impl Drop for OwnedData {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            ffi::sqlite3_free(self.ptr.as_ptr() as *mut c_void);
+        }
+    }
+}
+
+impl From<&[u8]> for OwnedData {
+    /// Copy `bytes` into a buffer allocated by `sqlite3_malloc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if SQLite cannot allocate the buffer.
+    fn from(bytes: &[u8]) -> OwnedData {
+        // never ask for a 0 byte allocation: sqlite3_malloc64 would return NULL
+        let len = bytes.len().max(1);
+        let ptr = unsafe { ffi::sqlite3_malloc64(len as ffi::sqlite3_uint64) } as *mut u8;
+        let ptr = NonNull::new(ptr).expect("sqlite3_malloc64 failed");
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), bytes.len());
+        }
+        OwnedData {
+            ptr,
+            sz: bytes.len(),
+        }
+    }
+}
+
+impl Deref for OwnedData {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.sz) }
+    }
+}
+
+impl fmt::Debug for OwnedData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedData").field("sz", &self.sz).finish()
+    }
+}
+
+impl Connection {
+    /// Serialize the `schema` database into a copy owned by the caller.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `schema` is not a known database or if SQLite
+    /// cannot allocate the copy.
+    pub fn serialize(&self, schema: DatabaseName<'_>) -> Result<OwnedData> {
+        let schema = schema.as_cstring()?;
+        let c = self.db.borrow();
+        // sqlite3_serialize also returns NULL for an unknown schema
+        if unsafe { ffi::sqlite3_db_filename(c.db(), schema.as_ptr()) }.is_null() {
+            return Err(unsafe { error_from_handle(c.db(), ffi::SQLITE_ERROR) });
+        }
+        // left untouched if the page count cannot be read
+        let mut sz = -1;
+        let ptr = unsafe { ffi::sqlite3_serialize(c.db(), schema.as_ptr(), &mut sz, 0) };
+        match NonNull::new(ptr) {
+            Some(ptr) => Ok(OwnedData {
+                ptr,
+                sz: sz as usize,
+            }),
+            // a database without any page: sqlite3_malloc64(0) returned NULL
+            None if sz == 0 => Ok(OwnedData::from(&[][..])),
+            None => Err(unsafe { error_from_handle(c.db(), ffi::SQLITE_NOMEM) }),
+        }
+    }
+
+    /// Serialize the `schema` database without copying it
+    /// (`SQLITE_SERIALIZE_NOCOPY`).
+    ///
+    /// Returns `None` when the database is not stored in contiguous memory,
+    /// i.e. unless it has been deserialized: use [`Connection::serialize`]
+    /// then.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `schema` is not a known database.
+    pub fn serialize_no_copy(
+        &mut self,
+        schema: DatabaseName<'_>,
+    ) -> Result<Option<SharedData<'_>>> {
+        let schema = schema.as_cstring()?;
+        let c = self.db.borrow();
+        // sqlite3_serialize also returns NULL for an unknown schema
+        if unsafe { ffi::sqlite3_db_filename(c.db(), schema.as_ptr()) }.is_null() {
+            return Err(unsafe { error_from_handle(c.db(), ffi::SQLITE_ERROR) });
+        }
+        let mut sz = 0;
+        let ptr = unsafe {
+            ffi::sqlite3_serialize(
+                c.db(),
+                schema.as_ptr(),
+                &mut sz,
+                ffi::SQLITE_SERIALIZE_NOCOPY as _,
+            )
+        };
+        Ok(NonNull::new(ptr).map(|ptr| SharedData {
+            phantom: PhantomData,
+            ptr,
+            sz: sz as usize,
+        }))
+    }
+
+    /// Replace the `schema` database with the content of `data`.
+    ///
+    /// Ownership of the buffer is transferred to SQLite, which frees it when
+    /// the database is closed. If `read_only` is `false`, SQLite is allowed to
+    /// grow the buffer as the database is modified.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `schema` is not a known database or if the
+    /// underlying SQLite call fails.
+    pub fn deserialize(
+        &mut self,
+        schema: DatabaseName<'_>,
+        data: OwnedData,
+        read_only: bool,
+    ) -> Result<()> {
+        let schema = schema.as_cstring()?;
+        let (ptr, sz) = data.into_raw();
+        let sz = sz as ffi::sqlite3_int64;
+        let flags = if read_only {
+            ffi::SQLITE_DESERIALIZE_FREEONCLOSE | ffi::SQLITE_DESERIALIZE_READONLY
+        } else {
+            ffi::SQLITE_DESERIALIZE_FREEONCLOSE | ffi::SQLITE_DESERIALIZE_RESIZEABLE
+        };
+        let c = self.db.borrow_mut();
+        // On failure, SQLite frees the buffer itself because of FREEONCLOSE.
+        let rc =
+            unsafe { ffi::sqlite3_deserialize(c.db(), schema.as_ptr(), ptr, sz, sz, flags as _) };
+        c.decode_result(rc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OwnedData;
+    use crate::{Connection, DatabaseName, Error, ErrorCode, Result};
+
+    #[test]
+    fn test_serialize_deserialize() -> Result<()> {
+        let mut src = Connection::open_in_memory()?;
+        src.execute_batch("CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES (42);")?;
+        // not stored in contiguous memory
+        assert!(src.serialize_no_copy(DatabaseName::Main)?.is_none());
+        let data = src.serialize(DatabaseName::Main)?;
+        assert!(!data.is_empty());
+
+        let mut dst = Connection::open_in_memory()?;
+        dst.deserialize(DatabaseName::Main, OwnedData::from(&data[..]), false)?;
+        let x: i64 = dst.query_row("SELECT x FROM foo", [], |r| r.get(0))?;
+        assert_eq!(42, x);
+        // resizeable: the database can grow
+        dst.execute_batch("INSERT INTO foo SELECT x + 1 FROM foo;")?;
+
+        // a deserialized database can be serialized without copy
+        let shared = dst.serialize_no_copy(DatabaseName::Main)?.unwrap();
+        assert!(shared.starts_with(b"SQLite format 3\0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_read_only() -> Result<()> {
+        let src = Connection::open_in_memory()?;
+        src.execute_batch("CREATE TABLE foo(x INTEGER);")?;
+        let data = src.serialize(DatabaseName::Main)?;
+
+        let mut dst = Connection::open_in_memory()?;
+        dst.deserialize(DatabaseName::Main, data, true)?;
+        dst.query_row("SELECT count(*) FROM foo", [], |r| r.get::<_, i64>(0))?;
+        assert!(dst.execute_batch("INSERT INTO foo VALUES (1);").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_empty() -> Result<()> {
+        let src = Connection::open_in_memory()?;
+        let data = src.serialize(DatabaseName::Main)?;
+        assert!(data.is_empty());
+
+        let mut dst = Connection::open_in_memory()?;
+        dst.deserialize(DatabaseName::Main, data, false)?;
+        dst.execute_batch("CREATE TABLE foo(x INTEGER);")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_unknown_schema() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        match db.serialize(DatabaseName::Attached("nope")) {
+            Err(Error::SqliteFailure(err, _)) => assert_eq!(ErrorCode::Unknown, err.code),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(db
+            .serialize_no_copy(DatabaseName::Attached("nope"))
+            .is_err());
+        Ok(())
+    }
+}
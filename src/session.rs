@@ -23,6 +23,8 @@ use crate::{errmsg_to_string, str_to_cstring, Connection, DatabaseName, Result};
 /// used to record changes to a database.
 pub struct Session<'conn> {
     phantom: PhantomData<&'conn Connection>,
+    #[cfg(feature = "preupdate_hook")]
+    conn: &'conn Connection,
     s: *mut ffi::sqlite3_session,
     filter: Option<Box<dyn Fn(&str) -> bool>>,
 }
@@ -35,6 +37,12 @@ impl Session<'_> {
     }
 
     /// Create a new session object
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if a
+    /// [`preupdate_hook`](Connection::preupdate_hook) is registered on `db`:
+    /// sessions are implemented on top of the same hook.
     #[inline]
     pub fn new_with_name<'conn>(
         db: &'conn Connection,
@@ -42,13 +50,24 @@ impl Session<'_> {
     ) -> Result<Session<'conn>> {
         let name = name.as_cstring()?;
 
-        let db = db.db.borrow_mut().db;
+        let c = db.db.borrow();
+        #[cfg(feature = "preupdate_hook")]
+        if c.free_preupdate_hook.is_some() {
+            return Err(crate::Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_MISUSE),
+                Some("a preupdate hook is registered".to_owned()),
+            ));
+        }
 
         let mut s: *mut ffi::sqlite3_session = ptr::null_mut();
-        check!(unsafe { ffi::sqlite3session_create(db, name.as_ptr(), &mut s) });
+        check!(unsafe { ffi::sqlite3session_create(c.db(), name.as_ptr(), &mut s) });
+        #[cfg(feature = "preupdate_hook")]
+        c.sessions.set(c.sessions.get() + 1);
 
         Ok(Session {
             phantom: PhantomData,
+            #[cfg(feature = "preupdate_hook")]
+            conn: db,
             s,
             filter: None,
         })
@@ -220,6 +239,11 @@ impl Drop for Session<'_> {
             self.table_filter(None::<fn(&str) -> bool>);
         }
         unsafe { ffi::sqlite3session_delete(self.s) };
+        #[cfg(feature = "preupdate_hook")]
+        {
+            let sessions = &self.conn.db.borrow().sessions;
+            sessions.set(sessions.get() - 1);
+        }
     }
 }
 
@@ -945,4 +969,25 @@ mod test {
         assert!(session.is_indirect());
         Ok(())
     }
+
+    #[test]
+    #[cfg(feature = "preupdate_hook")]
+    fn test_session_preupdate_hook() -> Result<()> {
+        use crate::hooks::PreUpdateCase;
+
+        let db = Connection::open_in_memory()?;
+        let hook = |_: Action, _: &str, _: &str, _: i64, _: i64, _: &PreUpdateCase<'_>| {};
+        {
+            let _session = Session::new(&db)?;
+            assert!(db.preupdate_hook(Some(hook)).is_err());
+            assert!(db
+                .preupdate_hook(None::<fn(Action, &str, &str, i64, i64, &PreUpdateCase<'_>)>)
+                .is_err());
+        }
+        db.preupdate_hook(Some(hook))?;
+        assert!(Session::new(&db).is_err());
+        db.preupdate_hook(None::<fn(Action, &str, &str, i64, i64, &PreUpdateCase<'_>)>)?;
+        Session::new(&db)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,214 @@
+//! Read transactions on a historical version of a WAL database.
+//!
+//! A [`Snapshot`] records the state of a database as seen by an open read
+//! transaction. Other connections to the same database file can then start a
+//! read transaction on exactly the same state, even if new transactions have
+//! been committed in the meantime.
+//!
+//! Requires SQLite to be compiled with `SQLITE_ENABLE_SNAPSHOT` (done by the
+//! `bundled` build when this feature is enabled) and the database to be in
+//! WAL mode.
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, DatabaseName, Result};
+//! fn same_view(reader1: &mut Connection, reader2: &mut Connection) -> Result<()> {
+//!     let tx1 = reader1.transaction()?;
+//!     // the read transaction must be started before taking a snapshot
+//!     tx1.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))?;
+//!     let snapshot = tx1.snapshot(DatabaseName::Main)?;
+//!     let tx2 = reader2.transaction_from_snapshot(DatabaseName::Main, &snapshot)?;
+//!     // tx1 and tx2 now see the same version of the database
+//!     Ok(())
+//! }
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ptr::{self, NonNull};
+
+use crate::ffi;
+use crate::{Connection, DatabaseName, Result, Transaction, TransactionBehavior};
+
+/// Identifies a historical version of a WAL database.
+///
+/// Snapshots of the same database file can be compared: a snapshot is less
+/// than another one if it is older. Comparing snapshots of different database
+/// files is meaningless.
+pub struct Snapshot {
+    ptr: NonNull<ffi::sqlite3_snapshot>,
+}
+
+// A snapshot is a plain value, not tied to the connection which created it.
+unsafe impl Send for Snapshot {}
+unsafe impl Sync for Snapshot {}
+
+impl Snapshot {
+    fn as_ptr(&self) -> *mut ffi::sqlite3_snapshot {
+        self.ptr.as_ptr()
+    }
+}
+
+impl Drop for Snapshot {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_snapshot_free(self.as_ptr()) };
+    }
+}
+
+impl PartialEq for Snapshot {
+    #[inline]
+    fn eq(&self, other: &Snapshot) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Snapshot {}
+
+impl PartialOrd for Snapshot {
+    #[inline]
+    fn partial_cmp(&self, other: &Snapshot) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Snapshot {
+    fn cmp(&self, other: &Snapshot) -> Ordering {
+        unsafe { ffi::sqlite3_snapshot_cmp(self.as_ptr(), other.as_ptr()) }.cmp(&0)
+    }
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot").field("ptr", &self.ptr).finish()
+    }
+}
+
+impl Connection {
+    /// Record the version of the `schema` database currently seen by this
+    /// connection.
+    ///
+    /// A read transaction must be open on `schema` (i.e. a transaction has
+    /// been started and at least one statement has read from `schema`), and
+    /// no write transaction.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if there is no read transaction on `schema`, if the
+    /// database is not in WAL mode or if the underlying SQLite call fails.
+    pub fn snapshot(&self, schema: DatabaseName<'_>) -> Result<Snapshot> {
+        let schema = schema.as_cstring()?;
+        let c = self.db.borrow();
+        let mut ptr = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_snapshot_get(c.db(), schema.as_ptr(), &mut ptr) };
+        c.decode_result(rc)?;
+        Ok(Snapshot {
+            ptr: NonNull::new(ptr).expect("sqlite3_snapshot_get returned a null snapshot"),
+        })
+    }
+
+    /// Begin a new read transaction on the `schema` database as it was when
+    /// `snapshot` was taken.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the snapshot is no longer available (e.g. the WAL
+    /// file has been checkpointed and restarted since), if the database is not
+    /// in WAL mode or if the underlying SQLite call fails.
+    pub fn transaction_from_snapshot(
+        &mut self,
+        schema: DatabaseName<'_>,
+        snapshot: &Snapshot,
+    ) -> Result<Transaction<'_>> {
+        Transaction::from_snapshot(self, schema, snapshot)
+    }
+
+    /// Try to make snapshots of the `schema` database taken by connections
+    /// which have since been closed available again.
+    ///
+    /// This scans the whole WAL file, so it is only useful after all the
+    /// connections to the database have been closed.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if a read transaction is open on `schema`, if the
+    /// database is not in WAL mode or if the underlying SQLite call fails.
+    pub fn snapshot_recover(&self, schema: DatabaseName<'_>) -> Result<()> {
+        let schema = schema.as_cstring()?;
+        let c = self.db.borrow();
+        let rc = unsafe { ffi::sqlite3_snapshot_recover(c.db(), schema.as_ptr()) };
+        c.decode_result(rc)
+    }
+}
+
+impl Transaction<'_> {
+    /// Begin a new read transaction on the `schema` database as it was when
+    /// `snapshot` was taken.
+    ///
+    /// See [`Connection::transaction_from_snapshot`].
+    pub fn from_snapshot<'conn>(
+        conn: &'conn mut Connection,
+        schema: DatabaseName<'_>,
+        snapshot: &Snapshot,
+    ) -> Result<Transaction<'conn>> {
+        let schema = schema.as_cstring()?;
+        // dropping the transaction on error rolls it back
+        let tx = Transaction::new(conn, TransactionBehavior::Deferred)?;
+        {
+            let c = tx.db.borrow();
+            let rc =
+                unsafe { ffi::sqlite3_snapshot_open(c.db(), schema.as_ptr(), snapshot.as_ptr()) };
+            c.decode_result(rc)?;
+        }
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Connection, DatabaseName, Result};
+
+    #[test]
+    fn test_snapshot() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db3");
+        let mut db1 = Connection::open(&path)?;
+        let mode: String =
+            db1.pragma_update_and_check(None, "journal_mode", &"WAL", |r| r.get(0))?;
+        assert_eq!("wal", mode);
+        db1.execute_batch("CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES (1);")?;
+        let db2 = Connection::open(&path)?;
+
+        let snapshot = {
+            let tx = db1.transaction()?;
+            tx.query_row("SELECT count(*) FROM foo", [], |_| Ok(()))?;
+            tx.snapshot(DatabaseName::Main)?
+        };
+        db2.execute("INSERT INTO foo VALUES (2)", [])?;
+
+        let count = |db: &Connection| -> Result<i64> {
+            db.query_row("SELECT count(*) FROM foo", [], |r| r.get(0))
+        };
+        {
+            let tx = db1.transaction_from_snapshot(DatabaseName::Main, &snapshot)?;
+            assert_eq!(1, count(&tx)?);
+        }
+        assert_eq!(2, count(&db1)?);
+
+        let newer = {
+            let tx = db1.transaction()?;
+            count(&tx)?;
+            tx.snapshot(DatabaseName::Main)?
+        };
+        assert!(snapshot < newer);
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_without_read_transaction() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Connection::open(temp_dir.path().join("test.db3"))?;
+        db.pragma_update_and_check(None, "journal_mode", &"WAL", |_| Ok(()))?;
+        assert!(db.snapshot(DatabaseName::Main).is_err());
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
+#[cfg(feature = "scanstatus")]
+use std::ffi::CStr;
 use std::iter::IntoIterator;
+#[cfg(feature = "scanstatus")]
+use std::os::raw::c_char;
 use std::os::raw::{c_int, c_void};
 #[cfg(feature = "array")]
 use std::rc::Rc;
@@ -16,7 +20,7 @@ use crate::vtab::array::{free_array, ARRAY_TYPE};
 
 /// A prepared statement.
 pub struct Statement<'conn> {
-    conn: &'conn Connection,
+    pub(crate) conn: &'conn Connection,
     pub(crate) stmt: RawStatement,
 }
 
@@ -796,6 +800,70 @@ impl Statement<'_> {
         self.stmt.get_status(status, true)
     }
 
+    /// Returns the scan status of each loop of the query plan, in the order
+    /// of the `EXPLAIN QUERY PLAN` output.
+    ///
+    /// Counters are accumulated over all the runs of the statement since it
+    /// was prepared or since [`reset_scan_status`](Statement::reset_scan_status)
+    /// was called.
+    ///
+    /// SQLite must be compiled with `SQLITE_ENABLE_STMT_SCANSTATUS` (done by
+    /// the `bundled` build when this feature is enabled).
+    #[cfg(feature = "scanstatus")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "scanstatus")))]
+    pub fn scan_status(&self) -> Vec<ScanStatus> {
+        let mut status = Vec::new();
+        for idx in 0.. {
+            let mut loops: i64 = 0;
+            if self.scan_status_op(idx, ffi::SQLITE_SCANSTAT_NLOOP, &mut loops) {
+                break;
+            }
+            let mut rows_visited: i64 = 0;
+            self.scan_status_op(idx, ffi::SQLITE_SCANSTAT_NVISIT, &mut rows_visited);
+            let mut estimated_rows: f64 = 0.0;
+            self.scan_status_op(idx, ffi::SQLITE_SCANSTAT_EST, &mut estimated_rows);
+            let mut select_id: c_int = 0;
+            self.scan_status_op(idx, ffi::SQLITE_SCANSTAT_SELECTID, &mut select_id);
+            let mut name: *const c_char = ptr::null();
+            self.scan_status_op(idx, ffi::SQLITE_SCANSTAT_NAME, &mut name);
+            let mut explain: *const c_char = ptr::null();
+            self.scan_status_op(idx, ffi::SQLITE_SCANSTAT_EXPLAIN, &mut explain);
+            status.push(ScanStatus {
+                loops,
+                rows_visited,
+                estimated_rows,
+                select_id,
+                name: unsafe { optional_string(name) },
+                explain: unsafe { optional_string(explain) },
+            });
+        }
+        status
+    }
+
+    /// Reset all the counters returned by
+    /// [`scan_status`](Statement::scan_status).
+    #[cfg(feature = "scanstatus")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "scanstatus")))]
+    #[inline]
+    pub fn reset_scan_status(&self) {
+        if self.stmt.is_null() {
+            return;
+        }
+        unsafe { ffi::sqlite3_stmt_scanstatus_reset(self.stmt.ptr()) };
+    }
+
+    // Returns `true` if `idx` is out of range (always for an empty statement).
+    #[cfg(feature = "scanstatus")]
+    fn scan_status_op<T>(&self, idx: c_int, op: c_int, out: &mut T) -> bool {
+        if self.stmt.is_null() {
+            return true;
+        }
+        let rc = unsafe {
+            ffi::sqlite3_stmt_scanstatus(self.stmt.ptr(), idx, op, out as *mut T as *mut c_void)
+        };
+        rc != 0
+    }
+
     #[cfg(feature = "extra_check")]
     #[inline]
     pub(crate) fn check_no_tail(&self) -> Result<()> {
@@ -924,6 +992,44 @@ impl Statement<'_> {
     }
 }
 
+#[cfg(feature = "scanstatus")]
+unsafe fn optional_string(p: *const c_char) -> Option<String> {
+    if p.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(p).to_string_lossy().into_owned())
+    }
+}
+
+/// Scan status of one loop of a query plan, see
+/// [`Statement::scan_status`].
+///
+/// See <https://sqlite.org/c3ref/c_scanstat_est.html> for more info.
+#[cfg(feature = "scanstatus")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scanstatus")))]
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ScanStatus {
+    /// Number of times the loop has run
+    /// (equivalent to SQLITE_SCANSTAT_NLOOP).
+    pub loops: i64,
+    /// Total number of rows visited by all the runs of the loop
+    /// (equivalent to SQLITE_SCANSTAT_NVISIT).
+    pub rows_visited: i64,
+    /// Number of rows visited by each run of the loop, as estimated by the
+    /// query planner (equivalent to SQLITE_SCANSTAT_EST).
+    pub estimated_rows: f64,
+    /// `select-id` of the loop, as in the `EXPLAIN QUERY PLAN` output
+    /// (equivalent to SQLITE_SCANSTAT_SELECTID).
+    pub select_id: i32,
+    /// Name of the index or table used by the loop
+    /// (equivalent to SQLITE_SCANSTAT_NAME).
+    pub name: Option<String>,
+    /// `EXPLAIN QUERY PLAN` text of the loop
+    /// (equivalent to SQLITE_SCANSTAT_EXPLAIN).
+    pub explain: Option<String>,
+}
+
 /// Prepared statement status counters.
 ///
 /// See `https://www.sqlite.org/c3ref/c_stmtstatus_counter.html`
@@ -1317,6 +1423,38 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    #[cfg(feature = "scanstatus")]
+    fn test_scan_status() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES (1); INSERT INTO foo VALUES (2);",
+        )?;
+        let mut stmt = db.prepare("SELECT x FROM foo")?;
+        let n = stmt.query_map([], |_| Ok(()))?.count();
+        assert_eq!(2, n);
+        let status = stmt.scan_status();
+        assert_eq!(1, status.len());
+        assert_eq!(1, status[0].loops);
+        assert_eq!(2, status[0].rows_visited);
+        assert_eq!(Some("foo"), status[0].name.as_deref());
+        assert!(status[0].explain.is_some());
+
+        stmt.reset_scan_status();
+        assert_eq!(0, stmt.scan_status()[0].loops);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "scanstatus")]
+    fn test_scan_status_empty_stmt() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let stmt = db.prepare("")?;
+        assert!(stmt.scan_status().is_empty());
+        stmt.reset_scan_status();
+        Ok(())
+    }
+
     #[test]
     fn test_bind_parameters() -> Result<()> {
         let db = Connection::open_in_memory()?;
@@ -0,0 +1,168 @@
+//! Run-time status counters
+use std::os::raw::c_int;
+
+use crate::error::error_from_sqlite_code;
+use crate::ffi;
+use crate::{Connection, Result};
+
+/// Per-connection status counters, see [`Connection::db_status`].
+///
+/// Note that depending on your version of SQLite, all of these
+/// may not be available.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DbStatus {
+    /// Equivalent to SQLITE_DBSTATUS_LOOKASIDE_USED
+    LookasideUsed = 0,
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_USED
+    CacheUsed = 1,
+    /// Equivalent to SQLITE_DBSTATUS_SCHEMA_USED
+    SchemaUsed = 2,
+    /// Equivalent to SQLITE_DBSTATUS_STMT_USED
+    StmtUsed = 3,
+    /// Equivalent to SQLITE_DBSTATUS_LOOKASIDE_HIT
+    LookasideHit = 4,
+    /// Equivalent to SQLITE_DBSTATUS_LOOKASIDE_MISS_SIZE
+    LookasideMissSize = 5,
+    /// Equivalent to SQLITE_DBSTATUS_LOOKASIDE_MISS_FULL
+    LookasideMissFull = 6,
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_HIT
+    CacheHit = 7,
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_MISS
+    CacheMiss = 8,
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_WRITE
+    CacheWrite = 9,
+    /// Equivalent to SQLITE_DBSTATUS_DEFERRED_FKS
+    DeferredFks = 10,
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_USED_SHARED
+    CacheUsedShared = 11,
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_SPILL
+    CacheSpill = 12,
+}
+
+/// Global status counters, see [`status`].
+///
+/// Note that depending on your version of SQLite, all of these
+/// may not be available.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Status {
+    /// Equivalent to SQLITE_STATUS_MEMORY_USED
+    MemoryUsed = 0,
+    /// Equivalent to SQLITE_STATUS_PAGECACHE_USED
+    PageCacheUsed = 1,
+    /// Equivalent to SQLITE_STATUS_PAGECACHE_OVERFLOW
+    PageCacheOverflow = 2,
+    /// Equivalent to SQLITE_STATUS_MALLOC_SIZE
+    MallocSize = 5,
+    /// Equivalent to SQLITE_STATUS_PARSER_STACK
+    ParserStack = 6,
+    /// Equivalent to SQLITE_STATUS_PAGECACHE_SIZE
+    PageCacheSize = 7,
+    /// Equivalent to SQLITE_STATUS_MALLOC_COUNT
+    MallocCount = 9,
+}
+
+/// Returns the current and highwater values of a global status counter.
+///
+/// If `reset` is `true`, the highwater mark is reset to the current value.
+///
+/// See [`sqlite3_status64()`](https://www.sqlite.org/c3ref/status.html).
+///
+/// # Failure
+///
+/// Will return `Err` if the counter is not supported by the linked SQLite
+/// library.
+pub fn status(op: Status, reset: bool) -> Result<(i64, i64)> {
+    #[cfg(feature = "modern_sqlite")] // 3.10.0
+    {
+        let mut current = 0;
+        let mut highwater = 0;
+        let rc = unsafe {
+            ffi::sqlite3_status64(op as c_int, &mut current, &mut highwater, reset as c_int)
+        };
+        check(rc).map(|_| (current, highwater))
+    }
+    #[cfg(not(feature = "modern_sqlite"))]
+    {
+        let mut current = 0;
+        let mut highwater = 0;
+        let rc = unsafe {
+            ffi::sqlite3_status(op as c_int, &mut current, &mut highwater, reset as c_int)
+        };
+        check(rc).map(|_| (i64::from(current), i64::from(highwater)))
+    }
+}
+
+impl Connection {
+    /// Returns the current and highwater values of a status counter for this
+    /// connection.
+    ///
+    /// If `reset` is `true`, the highwater mark (or, for the hit / miss /
+    /// write counters, the current value) is reset.
+    ///
+    /// See [`sqlite3_db_status()`](https://www.sqlite.org/c3ref/db_status.html).
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the counter is not supported by the linked SQLite
+    /// library.
+    pub fn db_status(&self, op: DbStatus, reset: bool) -> Result<(i32, i32)> {
+        let c = self.db.borrow();
+        let mut current = 0;
+        let mut highwater = 0;
+        let rc = unsafe {
+            ffi::sqlite3_db_status(
+                c.db(),
+                op as c_int,
+                &mut current,
+                &mut highwater,
+                reset as c_int,
+            )
+        };
+        check(rc).map(|_| (current, highwater))
+    }
+}
+
+// sqlite3_status and sqlite3_db_status do not set the connection error
+// message.
+fn check(rc: c_int) -> Result<()> {
+    if rc == ffi::SQLITE_OK {
+        Ok(())
+    } else {
+        Err(error_from_sqlite_code(rc, None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{status, DbStatus, Status};
+    use crate::{Connection, Result};
+
+    #[test]
+    fn test_db_status() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES (1);")?;
+        let (cache_used, _) = db.db_status(DbStatus::CacheUsed, false)?;
+        assert!(cache_used > 0);
+        let (schema_used, _) = db.db_status(DbStatus::SchemaUsed, false)?;
+        assert!(schema_used > 0);
+
+        db.query_row("SELECT x FROM foo", [], |_| Ok(()))?;
+        db.db_status(DbStatus::CacheHit, true)?;
+        let (hits, _) = db.db_status(DbStatus::CacheHit, false)?;
+        assert_eq!(0, hits);
+        Ok(())
+    }
+
+    #[test]
+    fn test_status() -> Result<()> {
+        let _db = Connection::open_in_memory()?;
+        let (current, highwater) = status(Status::MemoryUsed, false)?;
+        assert!(current > 0);
+        assert!(highwater >= current);
+        Ok(())
+    }
+}
@@ -1,7 +1,13 @@
 //! Tracing and profiling functions. Error and warning log.
 
+#[cfg(feature = "modern_sqlite")]
+use std::borrow::Cow;
 use std::ffi::{CStr, CString};
+#[cfg(feature = "modern_sqlite")]
+use std::marker::PhantomData;
 use std::mem;
+#[cfg(feature = "modern_sqlite")]
+use std::os::raw::c_uint;
 use std::os::raw::{c_char, c_int, c_void};
 use std::panic::catch_unwind;
 use std::ptr;
@@ -10,6 +16,8 @@ use std::time::Duration;
 use super::ffi;
 use crate::error::error_from_sqlite_code;
 use crate::{Connection, Result};
+#[cfg(feature = "modern_sqlite")]
+use crate::{InnerConnection, StatementStatus};
 
 /// Set up the process-wide SQLite error logging callback.
 ///
@@ -63,6 +71,77 @@ pub fn log(err_code: c_int, msg: &str) {
     }
 }
 
+#[cfg(feature = "modern_sqlite")] // 3.14.0
+bitflags::bitflags! {
+    /// Events traced by [`Connection::trace_v2`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+    pub struct TraceEventCodes: c_uint {
+        /// When a prepared statement first begins running.
+        const SQLITE_TRACE_STMT = ffi::SQLITE_TRACE_STMT as c_uint;
+        /// When a prepared statement finishes running.
+        const SQLITE_TRACE_PROFILE = ffi::SQLITE_TRACE_PROFILE as c_uint;
+        /// When a prepared statement generates a single row of result.
+        const SQLITE_TRACE_ROW = ffi::SQLITE_TRACE_ROW as c_uint;
+        /// When the database connection closes.
+        const SQLITE_TRACE_CLOSE = ffi::SQLITE_TRACE_CLOSE as c_uint;
+    }
+}
+
+/// Prepared statement being traced.
+#[cfg(feature = "modern_sqlite")]
+#[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+pub struct StmtRef<'s> {
+    ptr: *mut ffi::sqlite3_stmt,
+    phantom: PhantomData<&'s ()>,
+}
+
+#[cfg(feature = "modern_sqlite")]
+impl<'s> StmtRef<'s> {
+    /// SQL text of the statement, as prepared.
+    pub fn sql(&self) -> Cow<'s, str> {
+        unsafe { CStr::from_ptr(ffi::sqlite3_sql(self.ptr)) }.to_string_lossy()
+    }
+
+    /// SQL text of the statement with bound parameters expanded.
+    pub fn expanded_sql(&self) -> Option<String> {
+        unsafe {
+            let ptr = ffi::sqlite3_expanded_sql(self.ptr);
+            if ptr.is_null() {
+                return None;
+            }
+            let sql = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+            ffi::sqlite3_free(ptr as *mut c_void);
+            Some(sql)
+        }
+    }
+
+    /// Get the value for one of the status counters of the statement.
+    #[inline]
+    pub fn get_status(&self, status: StatementStatus) -> i32 {
+        unsafe { ffi::sqlite3_stmt_status(self.ptr, status as c_int, 0) }
+    }
+}
+
+/// Event delivered to a [`trace_v2`](Connection::trace_v2) callback.
+#[cfg(feature = "modern_sqlite")]
+#[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+#[non_exhaustive]
+pub enum TraceEvent<'s> {
+    /// A statement starts running (`SQLITE_TRACE_STMT`), with its SQL text
+    /// (or a `-- comment` for a trigger).
+    ///
+    /// Use [`StmtRef::expanded_sql`] to get the SQL text with bound
+    /// parameters expanded.
+    Stmt(StmtRef<'s>, Cow<'s, str>),
+    /// A statement has finished running (`SQLITE_TRACE_PROFILE`), with the
+    /// estimated time it took.
+    Profile(StmtRef<'s>, Duration),
+    /// A statement has generated a row (`SQLITE_TRACE_ROW`).
+    Row(StmtRef<'s>),
+    /// The connection is being closed (`SQLITE_TRACE_CLOSE`).
+    Close,
+}
+
 impl Connection {
     /// Register or clear a callback function that can be
     /// used for tracing the execution of SQL statements.
@@ -120,6 +199,104 @@ impl Connection {
             None => unsafe { ffi::sqlite3_profile(c.db(), None, ptr::null_mut()) },
         };
     }
+
+    /// Register or clear a callback function invoked for the events in
+    /// `mask`.
+    ///
+    /// This replaces [`trace`](Connection::trace) and
+    /// [`profile`](Connection::profile), which are built on deprecated
+    /// interfaces. There can only be a single tracer defined for each
+    /// database connection. Setting a new tracer clears the old one.
+    #[cfg(feature = "modern_sqlite")] // 3.14.0
+    #[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+    pub fn trace_v2<'c, F>(&'c self, mask: TraceEventCodes, tracer: Option<F>)
+    where
+        F: FnMut(TraceEvent<'_>) + Send + 'c,
+    {
+        self.db.borrow_mut().trace_v2(mask, tracer);
+    }
+}
+
+#[cfg(feature = "modern_sqlite")]
+impl InnerConnection {
+    fn trace_v2<'c, F>(&'c mut self, mask: TraceEventCodes, tracer: Option<F>)
+    where
+        F: FnMut(TraceEvent<'_>) + Send + 'c,
+    {
+        unsafe extern "C" fn call_boxed_closure<F>(
+            evt: c_uint,
+            ctx: *mut c_void,
+            p: *mut c_void,
+            x: *mut c_void,
+        ) -> c_int
+        where
+            F: FnMut(TraceEvent<'_>),
+        {
+            let stmt = || StmtRef {
+                ptr: p as *mut ffi::sqlite3_stmt,
+                phantom: PhantomData,
+            };
+            let event = match evt as c_int {
+                ffi::SQLITE_TRACE_STMT => {
+                    let sql = CStr::from_ptr(x as *const c_char).to_string_lossy();
+                    TraceEvent::Stmt(stmt(), sql)
+                }
+                ffi::SQLITE_TRACE_PROFILE => {
+                    let nanoseconds = *(x as *const i64);
+                    TraceEvent::Profile(stmt(), Duration::from_nanos(nanoseconds as u64))
+                }
+                ffi::SQLITE_TRACE_ROW => TraceEvent::Row(stmt()),
+                ffi::SQLITE_TRACE_CLOSE => TraceEvent::Close,
+                _ => return 0,
+            };
+            let _ = catch_unwind(|| {
+                let boxed_tracer: *mut F = ctx as *mut F;
+                (*boxed_tracer)(event);
+            });
+            0
+        }
+
+        let previous_tracer = self.tracer.take();
+        match tracer {
+            Some(tracer) => {
+                let boxed_tracer: *mut F = Box::into_raw(Box::new(tracer));
+                unsafe {
+                    ffi::sqlite3_trace_v2(
+                        self.db(),
+                        mask.bits(),
+                        Some(call_boxed_closure::<F>),
+                        boxed_tracer as *mut _,
+                    )
+                };
+                self.tracer = Some((boxed_tracer as *mut c_void, free_boxed_tracer::<F>));
+            }
+            None => unsafe {
+                ffi::sqlite3_trace_v2(self.db(), 0, None, ptr::null_mut());
+            },
+        }
+        if let Some((p, free)) = previous_tracer {
+            unsafe { free(p) };
+        }
+    }
+
+    /// Unregister the tracer (unless the connection has been closed), and
+    /// free it.
+    pub(crate) fn remove_tracer(&mut self) {
+        if let Some((p, free)) = self.tracer.take() {
+            if !self.db.is_null() {
+                unsafe { ffi::sqlite3_trace_v2(self.db, 0, None, ptr::null_mut()) };
+            }
+            unsafe { free(p) };
+        }
+    }
+}
+
+#[cfg(feature = "modern_sqlite")]
+pub(crate) type BoxedTracer = (*mut c_void, unsafe fn(*mut c_void));
+
+#[cfg(feature = "modern_sqlite")]
+unsafe fn free_boxed_tracer<F>(p: *mut c_void) {
+    drop(Box::from_raw(p as *mut F));
 }
 
 #[cfg(test)]
@@ -159,6 +336,68 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    #[cfg(feature = "modern_sqlite")]
+    fn test_trace_v2() -> Result<()> {
+        use super::{TraceEvent, TraceEventCodes};
+
+        let mut stmts = Vec::new();
+        let mut profiled = 0;
+        let mut rows = 0;
+        let mut closed = false;
+        {
+            let db = Connection::open_in_memory()?;
+            db.trace_v2(
+                TraceEventCodes::all(),
+                Some(|event: TraceEvent<'_>| match event {
+                    TraceEvent::Stmt(stmt, sql) => {
+                        assert_eq!("SELECT ?", stmt.sql());
+                        stmts.push((sql.into_owned(), stmt.expanded_sql()));
+                    }
+                    TraceEvent::Profile(stmt, _) => {
+                        assert_eq!("SELECT ?", stmt.sql());
+                        profiled += 1;
+                    }
+                    TraceEvent::Row(_) => rows += 1,
+                    TraceEvent::Close => closed = true,
+                }),
+            );
+            db.query_row("SELECT ?", [1i32], |_| Ok(()))?;
+        }
+        assert_eq!(
+            vec![("SELECT ?".to_owned(), Some("SELECT 1".to_owned()))],
+            stmts
+        );
+        assert_eq!(1, profiled);
+        assert_eq!(1, rows);
+        assert!(closed);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "modern_sqlite")]
+    fn test_trace_v2_mask() -> Result<()> {
+        use super::{TraceEvent, TraceEventCodes};
+
+        let mut events = 0;
+        let db = Connection::open_in_memory()?;
+        db.trace_v2(
+            TraceEventCodes::SQLITE_TRACE_ROW,
+            Some(|event: TraceEvent<'_>| {
+                assert!(matches!(event, TraceEvent::Row(_)));
+                events += 1;
+            }),
+        );
+        let mut stmt = db.prepare("SELECT 1 UNION ALL SELECT 2")?;
+        assert_eq!(2, stmt.query_map([], |_| Ok(()))?.count());
+        drop(stmt);
+        db.trace_v2(TraceEventCodes::empty(), None::<fn(TraceEvent<'_>)>);
+        db.query_row("SELECT 3", [], |_| Ok(()))?;
+        drop(db);
+        assert_eq!(2, events);
+        Ok(())
+    }
+
     #[test]
     fn test_profile() -> Result<()> {
         lazy_static! {
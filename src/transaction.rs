@@ -1,3 +1,5 @@
+#[cfg(feature = "modern_sqlite")]
+use crate::DatabaseName;
 use crate::{Connection, Result};
 use std::ops::Deref;
 
@@ -166,6 +168,20 @@ impl Transaction<'_> {
         Savepoint::with_depth_and_name(self.conn, 1, name)
     }
 
+    /// Returns `true` if the transaction holds a write lock on at least one
+    /// database, i.e. if a statement has written to a database since the
+    /// beginning of a `DEFERRED` transaction.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the underlying SQLite call fails.
+    #[cfg(feature = "modern_sqlite")] // 3.34.0
+    #[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+    #[inline]
+    pub fn has_write_lock(&self) -> Result<bool> {
+        Ok(self.conn.transaction_state(None)? == TransactionState::Write)
+    }
+
     /// Get the current setting for what happens to the transaction when it is
     /// dropped.
     #[inline]
@@ -293,6 +309,20 @@ impl Savepoint<'_> {
         Savepoint::with_depth_and_name(self.conn, self.depth + 1, name)
     }
 
+    /// Returns `true` if the savepoint holds a write lock on at least one
+    /// database, i.e. if a statement has written to a database since the
+    /// beginning of a `DEFERRED` transaction.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the underlying SQLite call fails.
+    #[cfg(feature = "modern_sqlite")] // 3.34.0
+    #[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+    #[inline]
+    pub fn has_write_lock(&self) -> Result<bool> {
+        Ok(self.conn.transaction_state(None)? == TransactionState::Write)
+    }
+
     /// Get the current setting for what happens to the savepoint when it is
     /// dropped.
     #[inline]
@@ -497,6 +527,33 @@ impl Connection {
     pub fn savepoint_with_name<T: Into<String>>(&mut self, name: T) -> Result<Savepoint<'_>> {
         Savepoint::with_name(self, name)
     }
+
+    /// Determine the transaction state of a database, or the highest
+    /// transaction state of all the databases if `db_name` is `None`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `db_name` is not a known database.
+    #[cfg(feature = "modern_sqlite")] // 3.34.0
+    #[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+    pub fn transaction_state(&self, db_name: Option<DatabaseName<'_>>) -> Result<TransactionState> {
+        self.db.borrow().txn_state(db_name)
+    }
+}
+
+/// Transaction state of a database, see
+/// [`Connection::transaction_state`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+#[cfg(feature = "modern_sqlite")] // 3.34.0
+#[cfg_attr(docsrs, doc(cfg(feature = "modern_sqlite")))]
+pub enum TransactionState {
+    /// Equivalent to SQLITE_TXN_NONE: no transaction is pending
+    None,
+    /// Equivalent to SQLITE_TXN_READ: a read transaction is open
+    Read,
+    /// Equivalent to SQLITE_TXN_WRITE: a write transaction is open
+    Write,
 }
 
 #[cfg(test)]
@@ -699,6 +756,36 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    #[cfg(feature = "modern_sqlite")]
+    fn test_transaction_state() -> Result<()> {
+        use crate::{DatabaseName, TransactionState};
+
+        let mut db = checked_memory_handle()?;
+        assert_eq!(TransactionState::None, db.transaction_state(None)?);
+        {
+            let tx = db.transaction()?;
+            assert!(!tx.has_write_lock()?);
+            tx.query_row("SELECT count(*) FROM foo", [], |_| Ok(()))?;
+            assert_eq!(
+                TransactionState::Read,
+                tx.transaction_state(Some(DatabaseName::Main))?
+            );
+            assert!(!tx.has_write_lock()?);
+            {
+                let mut tx = tx;
+                let sp = tx.savepoint()?;
+                insert(1, &sp)?;
+                assert!(sp.has_write_lock()?);
+            }
+        }
+        assert_eq!(TransactionState::None, db.transaction_state(None)?);
+        assert!(db
+            .transaction_state(Some(DatabaseName::Attached("nope")))
+            .is_err());
+        Ok(())
+    }
+
     fn insert(x: i32, conn: &Connection) -> Result<usize> {
         conn.execute("INSERT INTO foo VALUES(?)", [x])
     }
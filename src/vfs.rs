@@ -0,0 +1,955 @@
+//! Implement a custom [VFS](https://sqlite.org/vfs.html) in Rust.
+//!
+//! Follow these steps to use your own VFS:
+//! 1. Write implementations of the [`Vfs`] and [`VfsFile`] traits (and of
+//!    [`VfsShm`] if WAL mode must be supported).
+//! 2. Register your [`Vfs`] using [`register_vfs`].
+//! 3. Open connections with
+//!    [`Connection::open_with_flags_and_vfs`](crate::Connection::open_with_flags_and_vfs).
+//!
+//! Dynamic library loading, randomness, sleep and current time are inherited
+//! from the VFS which was the default one when [`register_vfs`] was called.
+//!
+//! ```rust,no_run
+//! # use rusqlite::vfs::{register_vfs, Vfs};
+//! # use rusqlite::{Connection, OpenFlags, Result};
+//! fn open_with<V: Vfs>(vfs: V) -> Result<Connection> {
+//!     register_vfs("my_vfs", vfs, false)?;
+//!     Connection::open_with_flags_and_vfs("my.db", OpenFlags::default(), "my_vfs")
+//! }
+//! ```
+
+use std::ffi::{CStr, CString};
+use std::mem;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
+use std::slice;
+use std::str;
+
+use crate::error::error_from_sqlite_code;
+use crate::ffi;
+use crate::{Error, Result};
+
+// Renamed from SQLITE_OPEN_MASTER_JOURNAL in 3.33.0
+const SQLITE_OPEN_SUPER_JOURNAL: c_int = 0x0000_4000;
+
+/// Type of file being opened by SQLite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OpenKind {
+    /// Main database file
+    MainDb,
+    /// Rollback journal of a main database
+    MainJournal,
+    /// Temporary database
+    TempDb,
+    /// Journal of a temporary database
+    TempJournal,
+    /// Transient database (e.g. used by `VACUUM`)
+    TransientDb,
+    /// Statement journal
+    SubJournal,
+    /// Super-journal of a multi-database transaction
+    SuperJournal,
+    /// Write-ahead log
+    Wal,
+}
+
+impl OpenKind {
+    fn from_flags(flags: c_int) -> Option<OpenKind> {
+        Some(match flags & 0x000F_FF00 {
+            ffi::SQLITE_OPEN_MAIN_DB => OpenKind::MainDb,
+            ffi::SQLITE_OPEN_MAIN_JOURNAL => OpenKind::MainJournal,
+            ffi::SQLITE_OPEN_TEMP_DB => OpenKind::TempDb,
+            ffi::SQLITE_OPEN_TEMP_JOURNAL => OpenKind::TempJournal,
+            ffi::SQLITE_OPEN_TRANSIENT_DB => OpenKind::TransientDb,
+            ffi::SQLITE_OPEN_SUBJOURNAL => OpenKind::SubJournal,
+            SQLITE_OPEN_SUPER_JOURNAL => OpenKind::SuperJournal,
+            ffi::SQLITE_OPEN_WAL => OpenKind::Wal,
+            _ => return None,
+        })
+    }
+}
+
+/// How a file must be opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenAccess {
+    /// Open an existing file in read-only mode.
+    ReadOnly,
+    /// Open an existing file for reading and writing.
+    ReadWrite,
+    /// Open a file for reading and writing, creating it if needed.
+    Create,
+    /// Create a new file, failing if it already exists.
+    CreateNew,
+}
+
+impl OpenAccess {
+    fn from_flags(flags: c_int) -> OpenAccess {
+        if flags & ffi::SQLITE_OPEN_CREATE != 0 {
+            if flags & ffi::SQLITE_OPEN_EXCLUSIVE != 0 {
+                OpenAccess::CreateNew
+            } else {
+                OpenAccess::Create
+            }
+        } else if flags & ffi::SQLITE_OPEN_READWRITE != 0 {
+            OpenAccess::ReadWrite
+        } else {
+            OpenAccess::ReadOnly
+        }
+    }
+}
+
+/// Options given to [`Vfs::open`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Type of file being opened
+    pub kind: OpenKind,
+    /// Access mode
+    pub access: OpenAccess,
+    /// Whether the file must be deleted when it is dropped
+    pub delete_on_close: bool,
+}
+
+/// Check done by [`Vfs::access`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessCheck {
+    /// Does the file exist?
+    Exists,
+    /// Is the file both readable and writable?
+    ReadWrite,
+    /// Is the file readable?
+    Read,
+}
+
+/// File lock levels.
+///
+/// (See [SQLite doc](https://sqlite.org/c3ref/c_lock_exclusive.html))
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LockLevel {
+    /// No lock is held.
+    None,
+    /// Any number of readers, no writer.
+    Shared,
+    /// A single process plans to write, readers are still allowed.
+    Reserved,
+    /// A single process waits for readers to go away in order to write.
+    Pending,
+    /// A single process is writing.
+    Exclusive,
+}
+
+impl LockLevel {
+    fn from_raw(level: c_int) -> LockLevel {
+        match level {
+            ffi::SQLITE_LOCK_SHARED => LockLevel::Shared,
+            ffi::SQLITE_LOCK_RESERVED => LockLevel::Reserved,
+            ffi::SQLITE_LOCK_PENDING => LockLevel::Pending,
+            ffi::SQLITE_LOCK_EXCLUSIVE => LockLevel::Exclusive,
+            _ => LockLevel::None,
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags given to [`VfsFile::sync`].
+    /// See [Synchronization Type Flags](https://sqlite.org/c3ref/c_sync_dataonly.html).
+    #[repr(C)]
+    pub struct SyncFlags: ::std::os::raw::c_int {
+        /// Normal fsync()
+        const SQLITE_SYNC_NORMAL = ffi::SQLITE_SYNC_NORMAL;
+        /// Mac OS X style fullsync
+        const SQLITE_SYNC_FULL = ffi::SQLITE_SYNC_FULL;
+        /// Only the data of the file needs to be synced, not its metadata
+        const SQLITE_SYNC_DATAONLY = ffi::SQLITE_SYNC_DATAONLY;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags given to [`VfsShm::lock`].
+    /// See [Flags for the xShmLock VFS method](https://sqlite.org/c3ref/c_shm_exclusive.html).
+    #[repr(C)]
+    pub struct ShmLockFlags: ::std::os::raw::c_int {
+        /// Release the lock
+        const SQLITE_SHM_UNLOCK = ffi::SQLITE_SHM_UNLOCK;
+        /// Acquire the lock
+        const SQLITE_SHM_LOCK = ffi::SQLITE_SHM_LOCK;
+        /// Shared lock
+        const SQLITE_SHM_SHARED = ffi::SQLITE_SHM_SHARED;
+        /// Exclusive lock
+        const SQLITE_SHM_EXCLUSIVE = ffi::SQLITE_SHM_EXCLUSIVE;
+    }
+}
+
+/// Virtual file system implementation.
+///
+/// The same instance is shared by all connections using the VFS, possibly
+/// from multiple threads.
+///
+/// Errors should be reported as [`Error::SqliteFailure`] with an
+/// `SQLITE_IOERR_*` extended code. Any other error is reported to SQLite as
+/// the generic I/O error of the failing operation.
+///
+/// (See [SQLite doc](https://sqlite.org/c3ref/vfs.html))
+pub trait Vfs: Send + Sync + 'static {
+    /// File implementation
+    type File: VfsFile;
+
+    /// Open a file.
+    ///
+    /// `path` is `None` for temporary files, in which case the
+    /// implementation must pick a name itself.
+    fn open(&self, path: Option<&str>, options: OpenOptions) -> Result<Self::File>;
+
+    /// Delete a file.
+    fn delete(&self, path: &str, sync_dir: bool) -> Result<()>;
+
+    /// Test for the access permissions of a file.
+    fn access(&self, path: &str, check: AccessCheck) -> Result<bool>;
+
+    /// Convert a relative path name to a full path name.
+    ///
+    /// Return `path` unchanged by default.
+    fn full_pathname(&self, path: &str) -> Result<String> {
+        Ok(path.to_owned())
+    }
+}
+
+/// File opened by a [`Vfs`].
+///
+/// The file is closed when dropped.
+///
+/// (See [SQLite doc](https://sqlite.org/c3ref/io_methods.html))
+pub trait VfsFile: Send + 'static {
+    /// Read at most `buf.len()` bytes starting at `offset` and return the
+    /// number of bytes read. Fewer bytes may be returned only when the end
+    /// of the file is reached.
+    fn read(&mut self, buf: &mut [u8], offset: u64) -> Result<usize>;
+
+    /// Write the whole `buf` starting at `offset`.
+    fn write(&mut self, buf: &[u8], offset: u64) -> Result<()>;
+
+    /// Truncate (or extend) the file to `size` bytes.
+    fn truncate(&mut self, size: u64) -> Result<()>;
+
+    /// Flush the content of the file to persistent storage.
+    fn sync(&mut self, flags: SyncFlags) -> Result<()>;
+
+    /// Return the current size of the file in bytes.
+    fn file_size(&mut self) -> Result<u64>;
+
+    /// Upgrade the lock held on the file to `level`.
+    ///
+    /// Return `Ok(false)` if the lock cannot be acquired because of another
+    /// connection (`SQLITE_BUSY`).
+    fn lock(&mut self, level: LockLevel) -> Result<bool>;
+
+    /// Downgrade the lock held on the file to `level` (either
+    /// [`LockLevel::Shared`] or [`LockLevel::None`]).
+    fn unlock(&mut self, level: LockLevel) -> Result<()>;
+
+    /// Check whether any connection holds a [`LockLevel::Reserved`] (or
+    /// higher) lock on the file.
+    fn check_reserved_lock(&mut self) -> Result<bool>;
+
+    /// Return the sector size of the underlying storage.
+    ///
+    /// 4096 by default.
+    fn sector_size(&mut self) -> c_int {
+        4096
+    }
+
+    /// Return the
+    /// [device characteristics](https://sqlite.org/c3ref/c_iocap_atomic.html)
+    /// of the underlying storage.
+    ///
+    /// 0 by default.
+    fn device_characteristics(&mut self) -> c_int {
+        0
+    }
+
+    /// Give access to the shared memory used by WAL mode.
+    ///
+    /// Without shared memory, WAL mode can only be used together with
+    /// `PRAGMA locking_mode=EXCLUSIVE`. `None` by default.
+    fn shm(&mut self) -> Option<&mut dyn VfsShm> {
+        None
+    }
+}
+
+/// Shared memory used by WAL mode.
+///
+/// # Safety
+///
+/// A region returned by [`map`](VfsShm::map) must be readable and writable
+/// for `size` bytes, and must stay valid (and at the same address) until
+/// [`unmap`](VfsShm::unmap) is called. Regions of the same database must be
+/// shared with every connection to this database.
+///
+/// (See [SQLite doc](https://sqlite.org/walformat.html#shm))
+pub unsafe trait VfsShm {
+    /// Return the `region`-th region of `size` bytes.
+    ///
+    /// If the region does not exist yet, it must be allocated (and zeroed)
+    /// when `extend` is true, otherwise `Ok(None)` must be returned.
+    fn map(&mut self, region: usize, size: usize, extend: bool) -> Result<Option<NonNull<u8>>>;
+
+    /// Acquire or release the `n` locks starting at `offset`.
+    ///
+    /// Return `Ok(false)` if the lock cannot be acquired because of another
+    /// connection (`SQLITE_BUSY`).
+    fn lock(&mut self, offset: usize, n: usize, flags: ShmLockFlags) -> Result<bool>;
+
+    /// Memory barrier.
+    ///
+    /// Issue a sequentially consistent fence by default.
+    fn barrier(&mut self) {
+        std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Release the regions mapped by this connection, deleting the shared
+    /// memory if `delete` is true.
+    fn unmap(&mut self, delete: bool) -> Result<()>;
+}
+
+/// Register `vfs` under `name`, making it the default VFS if `make_default`
+/// is true.
+///
+/// The VFS cannot be unregistered: it lives until the end of the process.
+/// Registering another VFS with the same name replaces it for new
+/// connections.
+///
+/// # Failure
+///
+/// Will return `Err` if `name` cannot be converted to a C-compatible string
+/// or if the underlying SQLite call fails.
+pub fn register_vfs<V: Vfs>(name: &str, vfs: V, make_default: bool) -> Result<()> {
+    let name = CString::new(name)?;
+    let parent = unsafe { ffi::sqlite3_vfs_find(ptr::null()) };
+    if parent.is_null() {
+        return Err(error_from_sqlite_code(
+            ffi::SQLITE_ERROR,
+            Some("no default VFS".to_owned()),
+        ));
+    }
+    let state = Box::new(VfsState {
+        vfs,
+        name,
+        io_methods: io_methods::<V::File>(1),
+        io_methods_shm: io_methods::<V::File>(2),
+    });
+    let raw = unsafe {
+        let mut raw: ffi::sqlite3_vfs = mem::zeroed();
+        raw.iVersion = 1;
+        raw.szOsFile = mem::size_of::<FileState<V::File>>() as c_int;
+        raw.mxPathname = (*parent).mxPathname;
+        raw.zName = state.name.as_ptr();
+        raw.xOpen = Some(rust_open::<V>);
+        raw.xDelete = Some(rust_delete::<V>);
+        raw.xAccess = Some(rust_access::<V>);
+        raw.xFullPathname = Some(rust_full_pathname::<V>);
+        // These ignore their VFS argument in the built-in VFSes.
+        raw.xDlOpen = (*parent).xDlOpen;
+        raw.xDlError = (*parent).xDlError;
+        raw.xDlSym = (*parent).xDlSym;
+        raw.xDlClose = (*parent).xDlClose;
+        raw.xRandomness = (*parent).xRandomness;
+        raw.xSleep = (*parent).xSleep;
+        raw.xCurrentTime = (*parent).xCurrentTime;
+        raw.xGetLastError = (*parent).xGetLastError;
+        raw.pAppData = Box::into_raw(state) as *mut c_void;
+        Box::into_raw(Box::new(raw))
+    };
+    let rc = unsafe { ffi::sqlite3_vfs_register(raw, make_default as c_int) };
+    if rc != ffi::SQLITE_OK {
+        unsafe {
+            let raw = Box::from_raw(raw);
+            drop(Box::from_raw(raw.pAppData as *mut VfsState<V>));
+        }
+        return Err(error_from_sqlite_code(rc, None));
+    }
+    Ok(())
+}
+
+struct VfsState<V: Vfs> {
+    vfs: V,
+    name: CString,
+    io_methods: ffi::sqlite3_io_methods,
+    io_methods_shm: ffi::sqlite3_io_methods,
+}
+
+// The first item must be `ffi::sqlite3_file`.
+#[repr(C)]
+struct FileState<F> {
+    base: ffi::sqlite3_file,
+    file: F,
+}
+
+fn io_methods<F: VfsFile>(version: c_int) -> ffi::sqlite3_io_methods {
+    let mut methods: ffi::sqlite3_io_methods = unsafe { mem::zeroed() };
+    methods.iVersion = version;
+    methods.xClose = Some(rust_close::<F>);
+    methods.xRead = Some(rust_read::<F>);
+    methods.xWrite = Some(rust_write::<F>);
+    methods.xTruncate = Some(rust_truncate::<F>);
+    methods.xSync = Some(rust_sync::<F>);
+    methods.xFileSize = Some(rust_file_size::<F>);
+    methods.xLock = Some(rust_lock::<F>);
+    methods.xUnlock = Some(rust_unlock::<F>);
+    methods.xCheckReservedLock = Some(rust_check_reserved_lock::<F>);
+    methods.xFileControl = Some(rust_file_control);
+    methods.xSectorSize = Some(rust_sector_size::<F>);
+    methods.xDeviceCharacteristics = Some(rust_device_characteristics::<F>);
+    if version >= 2 {
+        methods.xShmMap = Some(rust_shm_map::<F>);
+        methods.xShmLock = Some(rust_shm_lock::<F>);
+        methods.xShmBarrier = Some(rust_shm_barrier::<F>);
+        methods.xShmUnmap = Some(rust_shm_unmap::<F>);
+    }
+    methods
+}
+
+unsafe fn vfs_state<'a, V: Vfs>(vfs: *mut ffi::sqlite3_vfs) -> &'a VfsState<V> {
+    &*((*vfs).pAppData as *const VfsState<V>)
+}
+
+unsafe fn file_state<'a, F>(file: *mut ffi::sqlite3_file) -> &'a mut F {
+    &mut (*(file as *mut FileState<F>)).file
+}
+
+unsafe fn path<'a>(path: *const c_char) -> Result<&'a str> {
+    Ok(str::from_utf8(CStr::from_ptr(path).to_bytes())?)
+}
+
+/// Convert `result` to a SQLite result code, using `default` for errors
+/// which are not `Error::SqliteFailure`.
+fn result_code(result: Result<()>, default: c_int) -> c_int {
+    match result {
+        Ok(()) => ffi::SQLITE_OK,
+        Err(Error::SqliteFailure(err, _)) => err.extended_code,
+        Err(_) => default,
+    }
+}
+
+/// Run `f`, returning `default` if it panics: unwinding into SQLite is
+/// undefined behavior.
+fn catch_panic<T>(default: T, f: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+unsafe extern "C" fn rust_open<V: Vfs>(
+    vfs: *mut ffi::sqlite3_vfs,
+    z_name: *const c_char,
+    file: *mut ffi::sqlite3_file,
+    flags: c_int,
+    p_out_flags: *mut c_int,
+) -> c_int {
+    // xClose is only called when pMethods is set.
+    (*file).pMethods = ptr::null();
+    catch_panic(ffi::SQLITE_CANTOPEN, || {
+        let state = vfs_state::<V>(vfs);
+        let name = if z_name.is_null() {
+            None
+        } else {
+            match path(z_name) {
+                Ok(name) => Some(name),
+                Err(_) => return ffi::SQLITE_CANTOPEN,
+            }
+        };
+        let kind = match OpenKind::from_flags(flags) {
+            Some(kind) => kind,
+            None => return ffi::SQLITE_CANTOPEN,
+        };
+        let options = OpenOptions {
+            kind,
+            access: OpenAccess::from_flags(flags),
+            delete_on_close: flags & ffi::SQLITE_OPEN_DELETEONCLOSE != 0,
+        };
+        match state.vfs.open(name, options) {
+            Ok(mut f) => {
+                let methods = if f.shm().is_some() {
+                    &state.io_methods_shm
+                } else {
+                    &state.io_methods
+                };
+                ptr::write(
+                    file as *mut FileState<V::File>,
+                    FileState {
+                        base: ffi::sqlite3_file { pMethods: methods },
+                        file: f,
+                    },
+                );
+                if !p_out_flags.is_null() {
+                    *p_out_flags = flags;
+                }
+                ffi::SQLITE_OK
+            }
+            Err(err) => result_code(Err(err), ffi::SQLITE_CANTOPEN),
+        }
+    })
+}
+
+unsafe extern "C" fn rust_delete<V: Vfs>(
+    vfs: *mut ffi::sqlite3_vfs,
+    z_name: *const c_char,
+    sync_dir: c_int,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_DELETE, || {
+        let state = vfs_state::<V>(vfs);
+        let r = path(z_name).and_then(|name| state.vfs.delete(name, sync_dir != 0));
+        result_code(r, ffi::SQLITE_IOERR_DELETE)
+    })
+}
+
+unsafe extern "C" fn rust_access<V: Vfs>(
+    vfs: *mut ffi::sqlite3_vfs,
+    z_name: *const c_char,
+    flags: c_int,
+    p_res_out: *mut c_int,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_ACCESS, || {
+        let state = vfs_state::<V>(vfs);
+        let check = match flags {
+            ffi::SQLITE_ACCESS_READWRITE => AccessCheck::ReadWrite,
+            ffi::SQLITE_ACCESS_READ => AccessCheck::Read,
+            _ => AccessCheck::Exists,
+        };
+        let r = path(z_name).and_then(|name| state.vfs.access(name, check));
+        result_code(r.map(|b| *p_res_out = b as c_int), ffi::SQLITE_IOERR_ACCESS)
+    })
+}
+
+unsafe extern "C" fn rust_full_pathname<V: Vfs>(
+    vfs: *mut ffi::sqlite3_vfs,
+    z_name: *const c_char,
+    n_out: c_int,
+    z_out: *mut c_char,
+) -> c_int {
+    catch_panic(ffi::SQLITE_CANTOPEN, || {
+        let state = vfs_state::<V>(vfs);
+        let full = match path(z_name).and_then(|name| state.vfs.full_pathname(name)) {
+            Ok(full) => full,
+            Err(err) => return result_code(Err(err), ffi::SQLITE_CANTOPEN),
+        };
+        let bytes = full.as_bytes();
+        if bytes.len() >= n_out as usize || bytes.contains(&0) {
+            return ffi::SQLITE_CANTOPEN;
+        }
+        ptr::copy_nonoverlapping(bytes.as_ptr(), z_out as *mut u8, bytes.len());
+        *z_out.add(bytes.len()) = 0;
+        ffi::SQLITE_OK
+    })
+}
+
+unsafe extern "C" fn rust_close<F: VfsFile>(file: *mut ffi::sqlite3_file) -> c_int {
+    let r = catch_panic(ffi::SQLITE_IOERR_CLOSE, || {
+        ptr::drop_in_place(file as *mut FileState<F>);
+        ffi::SQLITE_OK
+    });
+    (*file).pMethods = ptr::null();
+    r
+}
+
+unsafe extern "C" fn rust_read<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+    buf: *mut c_void,
+    amt: c_int,
+    offset: ffi::sqlite3_int64,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_READ, || {
+        let buf = slice::from_raw_parts_mut(buf as *mut u8, amt as usize);
+        match file_state::<F>(file).read(buf, offset as u64) {
+            Ok(n) if n >= buf.len() => ffi::SQLITE_OK,
+            Ok(n) => {
+                // SQLite expects the unread part of the buffer to be zero-filled.
+                for b in &mut buf[n..] {
+                    *b = 0;
+                }
+                ffi::SQLITE_IOERR_SHORT_READ
+            }
+            Err(err) => result_code(Err(err), ffi::SQLITE_IOERR_READ),
+        }
+    })
+}
+
+unsafe extern "C" fn rust_write<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+    buf: *const c_void,
+    amt: c_int,
+    offset: ffi::sqlite3_int64,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_WRITE, || {
+        let buf = slice::from_raw_parts(buf as *const u8, amt as usize);
+        let r = file_state::<F>(file).write(buf, offset as u64);
+        result_code(r, ffi::SQLITE_IOERR_WRITE)
+    })
+}
+
+unsafe extern "C" fn rust_truncate<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+    size: ffi::sqlite3_int64,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_TRUNCATE, || {
+        let r = file_state::<F>(file).truncate(size as u64);
+        result_code(r, ffi::SQLITE_IOERR_TRUNCATE)
+    })
+}
+
+unsafe extern "C" fn rust_sync<F: VfsFile>(file: *mut ffi::sqlite3_file, flags: c_int) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_FSYNC, || {
+        let r = file_state::<F>(file).sync(SyncFlags::from_bits_truncate(flags));
+        result_code(r, ffi::SQLITE_IOERR_FSYNC)
+    })
+}
+
+unsafe extern "C" fn rust_file_size<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+    p_size: *mut ffi::sqlite3_int64,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_FSTAT, || {
+        let r = file_state::<F>(file).file_size();
+        result_code(
+            r.map(|size| *p_size = size as ffi::sqlite3_int64),
+            ffi::SQLITE_IOERR_FSTAT,
+        )
+    })
+}
+
+unsafe extern "C" fn rust_lock<F: VfsFile>(file: *mut ffi::sqlite3_file, level: c_int) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_LOCK, || {
+        match file_state::<F>(file).lock(LockLevel::from_raw(level)) {
+            Ok(true) => ffi::SQLITE_OK,
+            Ok(false) => ffi::SQLITE_BUSY,
+            Err(err) => result_code(Err(err), ffi::SQLITE_IOERR_LOCK),
+        }
+    })
+}
+
+unsafe extern "C" fn rust_unlock<F: VfsFile>(file: *mut ffi::sqlite3_file, level: c_int) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_UNLOCK, || {
+        let r = file_state::<F>(file).unlock(LockLevel::from_raw(level));
+        result_code(r, ffi::SQLITE_IOERR_UNLOCK)
+    })
+}
+
+unsafe extern "C" fn rust_check_reserved_lock<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+    p_res_out: *mut c_int,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_CHECKRESERVEDLOCK, || {
+        let r = file_state::<F>(file).check_reserved_lock();
+        result_code(
+            r.map(|b| *p_res_out = b as c_int),
+            ffi::SQLITE_IOERR_CHECKRESERVEDLOCK,
+        )
+    })
+}
+
+unsafe extern "C" fn rust_file_control(
+    _: *mut ffi::sqlite3_file,
+    _: c_int,
+    _: *mut c_void,
+) -> c_int {
+    ffi::SQLITE_NOTFOUND
+}
+
+unsafe extern "C" fn rust_sector_size<F: VfsFile>(file: *mut ffi::sqlite3_file) -> c_int {
+    catch_panic(0, || file_state::<F>(file).sector_size())
+}
+
+unsafe extern "C" fn rust_device_characteristics<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+) -> c_int {
+    catch_panic(0, || file_state::<F>(file).device_characteristics())
+}
+
+unsafe extern "C" fn rust_shm_map<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+    region: c_int,
+    size: c_int,
+    extend: c_int,
+    pp: *mut *mut c_void,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_SHMMAP, || {
+        *pp = ptr::null_mut();
+        let shm = match file_state::<F>(file).shm() {
+            Some(shm) => shm,
+            None => return ffi::SQLITE_IOERR_SHMMAP,
+        };
+        match shm.map(region as usize, size as usize, extend != 0) {
+            Ok(Some(p)) => {
+                *pp = p.as_ptr() as *mut c_void;
+                ffi::SQLITE_OK
+            }
+            Ok(None) => ffi::SQLITE_OK,
+            Err(err) => result_code(Err(err), ffi::SQLITE_IOERR_SHMMAP),
+        }
+    })
+}
+
+unsafe extern "C" fn rust_shm_lock<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+    offset: c_int,
+    n: c_int,
+    flags: c_int,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_SHMLOCK, || {
+        let shm = match file_state::<F>(file).shm() {
+            Some(shm) => shm,
+            None => return ffi::SQLITE_IOERR_SHMLOCK,
+        };
+        match shm.lock(
+            offset as usize,
+            n as usize,
+            ShmLockFlags::from_bits_truncate(flags),
+        ) {
+            Ok(true) => ffi::SQLITE_OK,
+            Ok(false) => ffi::SQLITE_BUSY,
+            Err(err) => result_code(Err(err), ffi::SQLITE_IOERR_SHMLOCK),
+        }
+    })
+}
+
+unsafe extern "C" fn rust_shm_barrier<F: VfsFile>(file: *mut ffi::sqlite3_file) {
+    catch_panic((), || {
+        if let Some(shm) = file_state::<F>(file).shm() {
+            shm.barrier();
+        }
+    })
+}
+
+unsafe extern "C" fn rust_shm_unmap<F: VfsFile>(
+    file: *mut ffi::sqlite3_file,
+    delete: c_int,
+) -> c_int {
+    catch_panic(ffi::SQLITE_IOERR_SHMMAP, || {
+        let r = match file_state::<F>(file).shm() {
+            Some(shm) => shm.unmap(delete != 0),
+            None => Ok(()),
+        };
+        result_code(r, ffi::SQLITE_IOERR_SHMMAP)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::ptr::NonNull;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use super::{
+        register_vfs, AccessCheck, LockLevel, OpenAccess, OpenOptions, ShmLockFlags, SyncFlags,
+        Vfs, VfsFile, VfsShm,
+    };
+    use crate::ffi;
+    use crate::{Connection, Error, OpenFlags, Result};
+
+    type Data = Arc<Mutex<Vec<u8>>>;
+    type Shm = Arc<Mutex<Vec<Box<[u8]>>>>;
+
+    #[derive(Default)]
+    struct MemVfs {
+        files: Arc<Mutex<HashMap<String, Data>>>,
+        shms: Arc<Mutex<HashMap<String, Shm>>>,
+        panic_on_read: Arc<AtomicBool>,
+    }
+
+    struct MemFile {
+        data: Data,
+        shm: Shm,
+        panic_on_read: Arc<AtomicBool>,
+    }
+
+    impl Vfs for MemVfs {
+        type File = MemFile;
+
+        fn open(&self, path: Option<&str>, options: OpenOptions) -> Result<MemFile> {
+            let path = path.unwrap_or("<temp>").to_owned();
+            let mut files = self.files.lock().unwrap();
+            let data = match files.get(&path) {
+                Some(data) => data.clone(),
+                None if options.access == OpenAccess::Create
+                    || options.access == OpenAccess::CreateNew =>
+                {
+                    let data = Arc::new(Mutex::new(Vec::new()));
+                    if !options.delete_on_close {
+                        files.insert(path.clone(), data.clone());
+                    }
+                    data
+                }
+                None => {
+                    return Err(Error::SqliteFailure(
+                        ffi::Error::new(ffi::SQLITE_CANTOPEN),
+                        None,
+                    ))
+                }
+            };
+            let shm = self.shms.lock().unwrap().entry(path).or_default().clone();
+            Ok(MemFile {
+                data,
+                shm,
+                panic_on_read: self.panic_on_read.clone(),
+            })
+        }
+
+        fn delete(&self, path: &str, _: bool) -> Result<()> {
+            self.files.lock().unwrap().remove(path);
+            Ok(())
+        }
+
+        fn access(&self, path: &str, _: AccessCheck) -> Result<bool> {
+            Ok(self.files.lock().unwrap().contains_key(path))
+        }
+    }
+
+    impl VfsFile for MemFile {
+        fn read(&mut self, buf: &mut [u8], offset: u64) -> Result<usize> {
+            assert!(!self.panic_on_read.load(Ordering::SeqCst), "read failed");
+            let data = self.data.lock().unwrap();
+            let start = (offset as usize).min(data.len());
+            let end = (start + buf.len()).min(data.len());
+            buf[..end - start].copy_from_slice(&data[start..end]);
+            Ok(end - start)
+        }
+
+        fn write(&mut self, buf: &[u8], offset: u64) -> Result<()> {
+            let mut data = self.data.lock().unwrap();
+            let end = offset as usize + buf.len();
+            if data.len() < end {
+                data.resize(end, 0);
+            }
+            data[offset as usize..end].copy_from_slice(buf);
+            Ok(())
+        }
+
+        fn truncate(&mut self, size: u64) -> Result<()> {
+            self.data.lock().unwrap().resize(size as usize, 0);
+            Ok(())
+        }
+
+        fn sync(&mut self, _: SyncFlags) -> Result<()> {
+            Ok(())
+        }
+
+        fn file_size(&mut self) -> Result<u64> {
+            Ok(self.data.lock().unwrap().len() as u64)
+        }
+
+        fn lock(&mut self, _: LockLevel) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn unlock(&mut self, _: LockLevel) -> Result<()> {
+            Ok(())
+        }
+
+        fn check_reserved_lock(&mut self) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn shm(&mut self) -> Option<&mut dyn VfsShm> {
+            Some(self)
+        }
+    }
+
+    unsafe impl VfsShm for MemFile {
+        fn map(&mut self, region: usize, size: usize, extend: bool) -> Result<Option<NonNull<u8>>> {
+            let mut regions = self.shm.lock().unwrap();
+            if regions.len() <= region {
+                if !extend {
+                    return Ok(None);
+                }
+                regions.resize_with(region + 1, || vec![0; size].into_boxed_slice());
+            }
+            Ok(NonNull::new(regions[region].as_mut_ptr()))
+        }
+
+        fn lock(&mut self, _: usize, _: usize, _: ShmLockFlags) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn unmap(&mut self, delete: bool) -> Result<()> {
+            if delete {
+                self.shm.lock().unwrap().clear();
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_vfs() -> Result<()> {
+        let vfs = MemVfs::default();
+        let files = vfs.files.clone();
+        register_vfs("test_mem_vfs", vfs, false)?;
+
+        let db =
+            Connection::open_with_flags_and_vfs("foo.db", OpenFlags::default(), "test_mem_vfs")?;
+        db.execute_batch(
+            "CREATE TABLE foo(x INTEGER);
+             BEGIN; INSERT INTO foo VALUES(1); INSERT INTO foo VALUES(2); COMMIT;",
+        )?;
+        assert!(files.lock().unwrap().contains_key("foo.db"));
+        // the rollback journal is deleted after each transaction
+        assert!(!files.lock().unwrap().contains_key("foo.db-journal"));
+
+        let db2 =
+            Connection::open_with_flags_and_vfs("foo.db", OpenFlags::default(), "test_mem_vfs")?;
+        let sum: i64 = db2.query_row("SELECT sum(x) FROM foo", [], |r| r.get(0))?;
+        assert_eq!(3, sum);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vfs_wal() -> Result<()> {
+        register_vfs("test_mem_vfs_wal", MemVfs::default(), false)?;
+        let db = Connection::open_with_flags_and_vfs(
+            "wal.db",
+            OpenFlags::default(),
+            "test_mem_vfs_wal",
+        )?;
+        let mode: String = db.query_row("PRAGMA journal_mode=WAL", [], |r| r.get(0))?;
+        assert_eq!("wal", mode);
+        db.execute_batch("CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES(42);")?;
+        let x: i64 = db.query_row("SELECT x FROM foo", [], |r| r.get(0))?;
+        assert_eq!(42, x);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vfs_open_missing_file() -> Result<()> {
+        register_vfs("test_mem_vfs_ro", MemVfs::default(), false)?;
+        let r = Connection::open_with_flags_and_vfs(
+            "missing.db",
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+            "test_mem_vfs_ro",
+        );
+        assert!(r.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_vfs_panic() -> Result<()> {
+        let vfs = MemVfs::default();
+        let panic_on_read = vfs.panic_on_read.clone();
+        register_vfs("test_mem_vfs_panic", vfs, false)?;
+
+        let db = Connection::open_with_flags_and_vfs(
+            "panic.db",
+            OpenFlags::default(),
+            "test_mem_vfs_panic",
+        )?;
+        db.execute_batch("CREATE TABLE foo(x INTEGER); INSERT INTO foo VALUES(1);")?;
+
+        let db2 = Connection::open_with_flags_and_vfs(
+            "panic.db",
+            OpenFlags::default(),
+            "test_mem_vfs_panic",
+        )?;
+        panic_on_read.store(true, Ordering::SeqCst);
+        let r: Result<i64> = db2.query_row("SELECT x FROM foo", [], |r| r.get(0));
+        assert!(r.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+//! Ensure the derive macros can be used outside `rusqlite` crate.
+#![cfg(feature = "derive")]
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::{Connection, Error, FromRow, Result, ToParams};
+use std::convert::TryFrom;
+
+#[derive(Debug, PartialEq, ToSql, FromSql)]
+struct UserId(i64);
+
+#[derive(Debug, PartialEq, ToSql, FromSql)]
+#[rusqlite(rename_all = "snake_case")]
+enum Status {
+    Active,
+    OnHold,
+    #[rusqlite(rename = "gone")]
+    Deleted,
+}
+
+#[derive(Debug, PartialEq, ToSql, FromSql)]
+#[rusqlite(integer)]
+enum Priority {
+    Low = 1,
+    High = 10,
+}
+
+fn yes_no(value: ValueRef<'_>) -> FromSqlResult<bool> {
+    match value.as_str()? {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(FromSqlError::InvalidType),
+    }
+}
+
+fn to_yes_no(value: &bool) -> Result<ToSqlOutput<'_>> {
+    Ok(ToSqlOutput::from(if *value { "yes" } else { "no" }))
+}
+
+fn unknown() -> String {
+    "unknown".to_owned()
+}
+
+#[derive(Debug, PartialEq, FromRow, ToParams)]
+#[rusqlite(rename_all = "camelCase")]
+struct User {
+    id: UserId,
+    full_name: String,
+    status: Status,
+    priority: Priority,
+    #[rusqlite(rename = "admin", from_sql = "yes_no", to_sql = "to_yes_no")]
+    is_admin: bool,
+    #[rusqlite(skip)]
+    cached: Option<String>,
+    #[rusqlite(default = "unknown")]
+    team: String,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Pair(i64, #[rusqlite(default)] Option<String>);
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Last {
+    #[rusqlite(index = 1)]
+    value: String,
+}
+
+fn db() -> Result<Connection> {
+    let db = Connection::open_in_memory()?;
+    db.execute_batch(
+        "CREATE TABLE user (id INTEGER PRIMARY KEY, fullName TEXT, status TEXT, priority INTEGER, admin TEXT);",
+    )?;
+    Ok(db)
+}
+
+#[test]
+fn test_from_row_to_params() -> Result<()> {
+    let db = db()?;
+    let user = User {
+        id: UserId(1),
+        full_name: "Ada".to_owned(),
+        status: Status::OnHold,
+        priority: Priority::High,
+        is_admin: true,
+        cached: Some("x".to_owned()),
+        team: "core".to_owned(),
+    };
+    db.execute(
+        "INSERT INTO user (id, fullName, status, priority, admin) VALUES (:id, :fullName, :status, :priority, :admin)",
+        &user,
+    )?;
+    let raw: (i64, String, String, i64, String) =
+        db.query_row("SELECT * FROM user", [], |r| TryFrom::try_from(r))?;
+    assert_eq!(
+        (
+            1,
+            "Ada".to_owned(),
+            "on_hold".to_owned(),
+            10,
+            "yes".to_owned()
+        ),
+        raw
+    );
+
+    let read = db.query_row("SELECT * FROM user", [], |r| User::try_from(r))?;
+    assert_eq!(
+        User {
+            cached: None,
+            team: "unknown".to_owned(),
+            ..user
+        },
+        read
+    );
+
+    // every parameter needs a value
+    let mut stmt = db.prepare("SELECT :id, :other")?;
+    match stmt.query(&read) {
+        Err(Error::InvalidParameterName(name)) => assert_eq!(":other", name),
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(_) => panic!("unexpected success"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_by_index() -> Result<()> {
+    let db = Connection::open_in_memory()?;
+    let pair = db.query_row("SELECT 1", [], |r| Pair::try_from(r))?;
+    assert_eq!(Pair(1, None), pair);
+    let pair = db.query_row("SELECT 1, 'a'", [], |r| Pair::try_from(r))?;
+    assert_eq!(Pair(1, Some("a".to_owned())), pair);
+    let last = db.query_row("SELECT 1, 'a'", [], |r| Last::try_from(r))?;
+    assert_eq!("a", last.value);
+    Ok(())
+}
+
+#[test]
+fn test_enums() -> Result<()> {
+    let db = Connection::open_in_memory()?;
+    let status: Status = db.query_row("SELECT ?", [Status::Deleted], |r| r.get(0))?;
+    assert_eq!(Status::Deleted, status);
+    assert_eq!(
+        "gone",
+        db.query_row("SELECT ?", [Status::Deleted], |r| r.get::<_, String>(0))?
+    );
+    let priority: Priority = db.query_row("SELECT 1", [], |r| r.get(0))?;
+    assert_eq!(Priority::Low, priority);
+
+    match db.query_row("SELECT 2", [], |r| r.get::<_, Priority>(0)) {
+        Err(Error::FromSqlConversionFailure(0, _, err)) => {
+            assert_eq!("invalid Priority value: 2", err.to_string())
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    assert!(db
+        .query_row("SELECT 'Active'", [], |r| r.get::<_, Status>(0))
+        .is_err());
+    assert!(db
+        .query_row("SELECT 1", [], |r| r.get::<_, Status>(0))
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_appender() -> Result<()> {
+    #[derive(ToParams)]
+    struct Row {
+        priority: Priority,
+        id: i64,
+    }
+
+    let db = db()?;
+    let mut appender = db.appender("user", &["id", "priority"])?;
+    appender.append(&Row {
+        id: 1,
+        priority: Priority::Low,
+    })?;
+    appender.finish()?;
+    let priority: Priority =
+        db.query_row("SELECT priority FROM user WHERE id = 1", [], |r| r.get(0))?;
+    assert_eq!(Priority::Low, priority);
+    assert!(matches!(
+        db.appender("user", &["admin"])?.append(&Row {
+            id: 2,
+            priority: Priority::High
+        }),
+        Err(Error::InvalidParameterName(_))
+    ));
+    Ok(())
+}